@@ -0,0 +1,232 @@
+// Search, neighbor lookup, graph building and indexed file benchmarks over a
+// synthetic galaxy and, optionally, a sample of a real cooked dataset.
+//
+//   cargo bench --bench search
+//
+// Settings come from the environment:
+//   NEUTRON_BENCH_SYSTEMS  synthetic galaxy size (default 50000)
+//   NEUTRON_BENCH_SEED     synthetic galaxy seed (default 1)
+//   NEUTRON_BENCH_JUMP     jump range in ly (default 50)
+//   NEUTRON_BENCH_ROUTES   route pairs per category (default 3)
+//   NEUTRON_BENCH_DATA     cooked data dir to benchmark as well
+//   NEUTRON_BENCH_SAMPLE   systems nearest Sol taken from it for the graph
+//                          building and file benchmarks (default 200000)
+use std::time::{Duration, Instant};
+use neutron_route_finder::*;
+
+const NEUTRON_GRAPH_JUMP_RANGE: f32 = 400.0;
+const FILE_ACCESS_COUNT: usize = 100_000;
+// Candidate systems considered when picking route pairs
+const PAIR_CANDIDATES: usize = 256;
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T where T::Err: std::fmt::Debug {
+    std::env::var(name).map(|v| v.parse().unwrap()).unwrap_or(default)
+}
+
+// Linux only: kB figures from /proc/self/status
+fn proc_status_kb(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(field))?;
+    line[field.len()..].trim().trim_end_matches("kB").trim().parse().ok()
+}
+
+// Writing 5 to clear_refs resets the peak resident set size
+fn reset_peak_memory() {
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
+
+struct Measurement {
+    name: String,
+    wall: Duration,
+    counters: SearchCounters,
+    // Peak resident memory while running, and how far that is above the
+    // memory in use beforehand
+    peak_kb: Option<u64>,
+    peak_growth_kb: Option<u64>,
+    note: String,
+}
+
+fn measure<T>(name: &str, f: impl FnOnce() -> T, note: impl Fn(&T) -> String) -> Measurement {
+    reset_peak_memory();
+    let before_kb = proc_status_kb("VmRSS:");
+    reset_search_counters();
+    let started = Instant::now();
+    let result = f();
+    let wall = started.elapsed();
+    let peak_kb = proc_status_kb("VmHWM:");
+    Measurement {
+        name: name.to_string(),
+        wall,
+        counters: search_counters(),
+        peak_kb,
+        peak_growth_kb: peak_kb.zip(before_kb).map(|(peak, before)| peak.saturating_sub(before)),
+        note: note(&result),
+    }
+}
+
+fn report(dataset: &str, measurements: &[Measurement]) {
+    println!();
+    println!("{}", dataset);
+    println!("{:<48} {:>12} {:>12} {:>10} {:>10} {:>10}  note", "benchmark", "wall ms", "expansions", "neutron", "peak MB", "+MB");
+    let mb = |kb: Option<u64>| kb.map(|kb| format!("{:.1}", kb as f64 / 1024.0)).unwrap_or_else(|| "-".to_string());
+    for m in measurements {
+        println!("{:<48} {:>12.3} {:>12} {:>10} {:>10} {:>10}  {}", m.name, m.wall.as_secs_f64() * 1000.0, m.counters.expansions, m.counters.neutron_expansions, mb(m.peak_kb), mb(m.peak_growth_kb), m.note);
+    }
+}
+
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: u32) -> u32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % n as u64) as u32
+    }
+}
+
+// Representative pairs: a few jumps apart, many jumps apart, across the
+// widest span of well-connected systems, and between the quarter of
+// candidates with the fewest systems within one jump.
+fn route_pairs<S: Systems>(systems: &S, jump_distance: Float, count: usize) -> Vec<(&'static str, u32, u32)> {
+    let mut rng = Lcg(0x5eed);
+    let mut candidates: Vec<(u32, usize)> = (0..PAIR_CANDIDATES).map(|_| {
+        let idx = rng.below(systems.len());
+        (idx, neighbors(systems, idx, jump_distance).len())
+    }).collect();
+    candidates.sort_by_key(|&(idx, density)| (std::cmp::Reverse(density), idx));
+    candidates.dedup_by_key(|&mut (idx, _)| idx);
+    let connected: Vec<u32> = candidates[..candidates.len() * 3 / 4].iter().map(|&(idx, _)| idx).collect();
+    let sparse: Vec<u32> = candidates[candidates.len() * 3 / 4..].iter().filter(|&&(_, density)| density > 0).map(|&(idx, _)| idx).collect();
+
+    let all_pairs = |from: &[u32]| -> Vec<(Float, u32, u32)> {
+        let mut pairs: Vec<(Float, u32, u32)> = from.iter().flat_map(|&a| from.iter().map(move |&b| (a, b)))
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| (system_distance(systems, a, b), a, b))
+            .collect();
+        pairs.sort();
+        pairs
+    };
+    let connected_pairs = all_pairs(&connected);
+    let in_range = |min: f32, max: f32| -> Vec<(u32, u32)> {
+        connected_pairs.iter().filter(|(d, _, _)| f32::from(*d) >= min && f32::from(*d) <= max).map(|&(_, a, b)| (a, b)).collect()
+    };
+    let spread = |pairs: Vec<(u32, u32)>| -> Vec<(u32, u32)> {
+        let step = (pairs.len() / count.max(1)).max(1);
+        pairs.into_iter().step_by(step).take(count).collect()
+    };
+    let jump = f32::from(jump_distance);
+    let mut retval = Vec::new();
+    retval.extend(spread(in_range(2.0 * jump, 8.0 * jump)).into_iter().map(|(a, b)| ("short", a, b)));
+    retval.extend(spread(in_range(20.0 * jump, 60.0 * jump)).into_iter().map(|(a, b)| ("medium", a, b)));
+    retval.extend(connected_pairs.iter().rev().take(count).map(|&(_, a, b)| ("galaxy-crossing", a, b)));
+    retval.extend(spread(all_pairs(&sparse).into_iter().map(|(_, a, b)| (a, b)).collect()).into_iter().map(|(a, b)| ("sparse-region", a, b)));
+    retval
+}
+
+fn route_note(route: &Option<Vec<u32>>) -> String {
+    match route {
+        Some(route) => format!("{} jumps", route.len()),
+        None => "no route".to_string(),
+    }
+}
+
+fn bench_routes<S: Systems>(systems: &S, neutrons: &NeutronFileMap, jump_distance: Float, count: usize, measurements: &mut Vec<Measurement>) {
+    for (category, start, goal) in route_pairs(systems, jump_distance, count) {
+        let label = format!("{} {:.0} ly", category, f32::from(system_distance(systems, start, goal)));
+        measurements.push(measure(&format!("a_star {}", label), || a_star(systems, start, goal, jump_distance, None), route_note));
+        measurements.push(measure(&format!("a_star exact {}", label), || a_star_with_mode(systems, start, goal, jump_distance, None, SearchMode::Exact), route_note));
+        measurements.push(measure(&format!("neutron_a_star {}", label), || neutron_a_star(systems, neutrons, start, goal, jump_distance), route_note));
+    }
+}
+
+fn bench_neighbors<S: Systems>(systems: &S, jump_distance: Float, measurements: &mut Vec<Measurement>) {
+    let mut rng = Lcg(0xbe7c);
+    let idxs: Vec<u32> = (0..FILE_ACCESS_COUNT / 10).map(|_| rng.below(systems.len())).collect();
+    measurements.push(measure(&format!("neighbors x{}", idxs.len()), || {
+        idxs.iter().map(|&idx| neighbors(systems, idx, jump_distance).len()).sum::<usize>()
+    }, |total| format!("{} neighbors", total)));
+}
+
+// Graph building and indexed file access over `systems`, which must be sorted
+fn bench_files(systems: &[StarSystem], scratch_dir: &std::path::Path, measurements: &mut Vec<Measurement>) -> NeutronFileMap {
+    let neutrons = {
+        let mut built = Vec::new();
+        measurements.push(measure("make_neutron_star_systems", || {
+            built = make_neutron_star_systems(systems, NEUTRON_GRAPH_JUMP_RANGE);
+        }, |_| String::new()));
+        let edges: usize = built.iter().map(|n| n.neighbors.len()).sum();
+        measurements.last_mut().unwrap().note = format!("{} neutron stars, {} edges", built.len(), edges);
+        built
+    };
+    let neutron_path = scratch_dir.join("neutrons.nbr");
+    let neutron_path = neutron_path.to_str().unwrap();
+    write_neutron_file(&neutrons, neutron_path).unwrap();
+    drop(neutrons);
+
+    let records: Vec<StarSystemRecord> = systems.iter().cloned().map(|s| s.into()).collect();
+    let indexed_path = scratch_dir.join("systems.idx");
+    let indexed_path = indexed_path.to_str().unwrap();
+    write_indexed_file(&records, indexed_path).unwrap();
+    drop(records);
+    let mut rng = Lcg(0xf11e);
+    let idxs: Vec<u32> = (0..FILE_ACCESS_COUNT).map(|_| rng.below(systems.len() as u32)).collect();
+    let indexed: IndexedFileMap<StarSystemRecord> = IndexedFileMap::new(indexed_path);
+    measurements.push(measure(&format!("IndexedFileMap get x{}", idxs.len()), || {
+        idxs.iter().map(|&idx| indexed.get(idx).name.len()).sum::<usize>()
+    }, |_| String::new()));
+    let capacity = systems.len() / 10 + 1;
+    for (label, policy) in [("unbounded".to_string(), CachePolicy::Unbounded), (format!("clock:{}", capacity), CachePolicy::Clock { capacity })] {
+        let cached: CachedIndexedFileMap<StarSystemRecord, StarSystem> = CachedIndexedFileMap::with_policy(indexed_path, policy);
+        measurements.push(measure(&format!("CachedIndexedFileMap {} x{}", label, idxs.len()), || {
+            idxs.iter().map(|&idx| cached.get(idx).name.len()).sum::<usize>()
+        }, |_| {
+            let stats = cached.stats();
+            format!("{} hits, {} misses, {} evictions", stats.hits, stats.misses, stats.evictions)
+        }));
+    }
+    NeutronFileMap::new(neutron_path)
+}
+
+fn main() {
+    let system_count: usize = env_or("NEUTRON_BENCH_SYSTEMS", 50_000);
+    let seed: u64 = env_or("NEUTRON_BENCH_SEED", 1);
+    let jump_distance = Float::from(env_or("NEUTRON_BENCH_JUMP", 50.0f32));
+    let route_count: usize = env_or("NEUTRON_BENCH_ROUTES", 3);
+    let scratch_dir = std::env::temp_dir().join(format!("neutron_route_finder-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&scratch_dir).unwrap();
+
+    // Small and dense enough that most of it is reachable at the default range
+    let galaxy = SyntheticGalaxy {
+        seed,
+        system_count,
+        radius: 2500.0,
+        disk_scale_length: 700.0,
+        disk_scale_height: 120.0,
+        core_radius: 400.0,
+        cluster_radius: 60.0,
+        neutron_ratio: 0.02,
+        ..SyntheticGalaxy::default()
+    };
+    let mut systems: Vec<StarSystem> = galaxy.generate().into_iter().map(|r| r.into()).collect();
+    systems.sort();
+    let mut measurements = Vec::new();
+    let neutrons = bench_files(&systems, &scratch_dir, &mut measurements);
+    let systems = VecMap::new(systems);
+    bench_neighbors(&systems, jump_distance, &mut measurements);
+    bench_routes(&systems, &neutrons, jump_distance, route_count, &mut measurements);
+    report(&format!("Synthetic galaxy: {} systems, seed {}, jump range {}", system_count, seed, f32::from(jump_distance)), &measurements);
+
+    if let Ok(data_dir) = std::env::var("NEUTRON_BENCH_DATA") {
+        let sample_size: u32 = env_or("NEUTRON_BENCH_SAMPLE", 200_000);
+        let (manifest, systems, neutrons) = open_data_dir(&data_dir).unwrap();
+        manifest.check_jump_distance(jump_distance).unwrap();
+        let mut measurements = Vec::new();
+        // Sorted by distance from Sol, so this is the bubble around Sol
+        let sample: Vec<StarSystem> = (0..sample_size.min(systems.len())).map(|idx| systems.get(idx)).collect();
+        bench_files(&sample, &scratch_dir, &mut measurements);
+        drop(sample);
+        bench_neighbors(&systems, jump_distance, &mut measurements);
+        bench_routes(&systems, &neutrons, jump_distance, route_count, &mut measurements);
+        report(&format!("{}: {} systems (graph and file benchmarks on the {} nearest Sol), jump range {}", data_dir, systems.len(), sample_size.min(systems.len()), f32::from(jump_distance)), &measurements);
+    }
+    std::fs::remove_dir_all(&scratch_dir).unwrap();
+}
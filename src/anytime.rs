@@ -0,0 +1,79 @@
+use crate::budget::Budget;
+use crate::{a_star_budgeted, exact_h_jumps, neutron_graph_run, system_distance, Float, NeutronFileMap, SearchMode, SearchOptions, SearchOutcome, Systems};
+
+// Weights for successive passes of neutron_a_star_anytime: greedy first,
+// plain A* last
+pub const ANYTIME_WEIGHTS: [f32; 5] = [5.0, 3.0, 2.0, 1.5, 1.0];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnytimeRoute {
+    // As from neutron_a_star: the start excluded, the goal last
+    pub route: Vec<u32>,
+    // Weight of the pass that found it
+    pub weight: f32,
+    // The route takes at most this many times the fewest jumps of any route.
+    // 1.0 once the exact search has confirmed it.
+    pub bound: f32,
+}
+
+// Runs weighted A* over the neutron graph once per weight, each pass pruned by
+// the best route so far, and calls `on_route` whenever the route gets shorter
+// or its bound gets tighter. The last weight should be 1.0, making the last
+// pass plain A*. In SearchMode::Exact an exact search over every system, no
+// longer than the best route, follows and settles the bound. Stopping early
+// through `options` gives the best route found by then.
+#[allow(clippy::too_many_arguments)]
+pub fn neutron_a_star_anytime<S: Systems, F: FnMut(&AnytimeRoute)>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float, weights: &[f32], options: &SearchOptions, mut on_route: F) -> SearchOutcome {
+    let mut budget = Budget::new(options);
+    budget.progress().begin(&format!("Anytime route {} to {}", systems.name(start_idx), systems.name(goal_idx)), None);
+    // No route can beat this, whatever the neutron graph holds. The graph
+    // passes' bounds go by this alone: their estimates can overshoot, so what
+    // they leave in the queue proves nothing.
+    let fewest_possible = exact_h_jumps(systems, start_idx, system_distance(systems, start_idx, goal_idx), jump_distance).max(1);
+    let mut best: Option<AnytimeRoute> = None;
+    for &weight in weights {
+        let incumbent = best.as_ref().map(|best| best.route.clone());
+        let route = match neutron_graph_run(systems, neutron_systems, start_idx, goal_idx, jump_distance, incumbent, weight, &mut budget) {
+            SearchOutcome::Found(route) => route,
+            SearchOutcome::NoRoute => continue,
+            SearchOutcome::Stopped { reason, best: stopped_best } => {
+                let best = best.map(|best| best.route).into_iter().chain(stopped_best).min_by_key(|route| route.len());
+                budget.end();
+                return SearchOutcome::Stopped { reason, best };
+            }
+        };
+        if best.as_ref().is_none_or(|best| route.len() < best.route.len()) {
+            let bound = (route.len() as f32 / fewest_possible as f32).max(1.0);
+            budget.progress().note(&format!("Weight {}: {} jumps, at most {:.2}x the fewest", weight, route.len(), bound));
+            let improved = AnytimeRoute { route, weight, bound };
+            on_route(&improved);
+            best = Some(improved);
+        }
+    }
+    if options.mode == SearchMode::Exact {
+        let max_jumps = best.as_ref().map(|best| best.route.len() as i64);
+        let (route, weight) = match a_star_budgeted(systems, start_idx, goal_idx, jump_distance, max_jumps, SearchMode::Exact, &mut budget) {
+            SearchOutcome::Found(route) => (route, 1.0),
+            // Nothing beats the graph's route
+            SearchOutcome::NoRoute => match &best {
+                Some(best) => (best.route.clone(), best.weight),
+                None => {
+                    budget.end();
+                    return SearchOutcome::NoRoute;
+                }
+            },
+            SearchOutcome::Stopped { reason, .. } => {
+                budget.end();
+                return SearchOutcome::Stopped { reason, best: best.map(|best| best.route) };
+            }
+        };
+        if best.as_ref().is_none_or(|best| route.len() < best.route.len() || best.bound > 1.0) {
+            budget.progress().note(&format!("Exact: {} jumps, the fewest", route.len()));
+            let exact = AnytimeRoute { route, weight, bound: 1.0 };
+            on_route(&exact);
+            best = Some(exact);
+        }
+    }
+    budget.end();
+    best.map_or(SearchOutcome::NoRoute, |best| SearchOutcome::Found(best.route))
+}
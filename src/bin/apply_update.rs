@@ -0,0 +1,16 @@
+use std::env;
+use neutron_route_finder::{read_delta, update_data_dir};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("Need data dir and delta");
+    }
+    let data_dir = &args[1];
+    let delta_file = &args[2];
+
+    let delta = read_delta(delta_file);
+    let (manifest, summary, recomputed) = update_data_dir(data_dir, delta).unwrap();
+    println!("Added {}, updated {}, removed {}, {} neutron star changes", summary.added, summary.updated, summary.removed, summary.changed_neutron_positions.len());
+    println!("Recomputed {} of {} neutron neighbor lists", recomputed, manifest.neutron_count);
+}
@@ -0,0 +1,35 @@
+use std::env;
+use neutron_route_finder::{CookOptions, ProgressStyle, PruningPolicy, cook};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut positional = Vec::new();
+    let mut options = CookOptions {
+        input: String::new(),
+        data_dir: String::new(),
+        max_jump_distance: 400.0,
+        pruning: PruningPolicy::default(),
+        sqlite: false,
+        force: false,
+        progress: ProgressStyle::Bar.reporter(),
+    };
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--jump-range" => options.max_jump_distance = args_iter.next().expect("--jump-range needs a value").parse().unwrap(),
+            "--pruning" => options.pruning.rule = args_iter.next().expect("--pruning needs a rule").parse().unwrap(),
+            "--max-neighbor-distance" => options.pruning.max_distance = args_iter.next().expect("--max-neighbor-distance needs a value").parse().unwrap(),
+            "--sqlite" => options.sqlite = true,
+            "--force" => options.force = true,
+            "--progress" => options.progress = args_iter.next().expect("--progress needs a style").parse::<ProgressStyle>().unwrap().reporter(),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if positional.len() < 2 {
+        panic!("Usage: cook <raw dump (csv, json lines or bincode)> <data dir> [--jump-range N] [--pruning occlusion|octant:K|yao:N|theta:N|rng] [--max-neighbor-distance N] [--sqlite] [--force] [--progress bar|log|none]");
+    }
+    options.input = positional[0].clone();
+    options.data_dir = positional[1].clone();
+    let manifest = cook(&options).unwrap();
+    println!("Cooked {} systems, {} neutron stars into {}", manifest.system_count, manifest.neutron_count, options.data_dir);
+}
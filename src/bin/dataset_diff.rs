@@ -0,0 +1,83 @@
+use std::env;
+use std::path::Path;
+use neutron_route_finder::{check_unique_id64s, diff_datasets, write_delta, StarSystemRecord};
+use neutron_route_finder::diff::coordinate_shift;
+use neutron_route_finder::pipeline::{read_star_system_records, SYSTEMS_BINCODE_FILE};
+
+const SUMMARY_EXAMPLES: usize = 10;
+
+// A cooked data directory stands for the systems it was cooked from
+fn read_dataset(path: &str) -> Vec<StarSystemRecord> {
+    let records = if Path::new(path).is_dir() {
+        read_star_system_records(Path::new(path).join(SYSTEMS_BINCODE_FILE).to_str().unwrap())
+    } else {
+        read_star_system_records(path)
+    };
+    check_unique_id64s(&records).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    records
+}
+
+fn describe(record: &StarSystemRecord) -> String {
+    format!("{} ({}) at ({}, {}, {})", record.name, record.mainStarType, record.Coord_X, record.Coord_Y, record.Coord_Z)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut positional = Vec::new();
+    let mut details = false;
+    let mut delta_file = None;
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--details" => details = true,
+            "--delta" => delta_file = Some(args_iter.next().expect("--delta needs a file").clone()),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if positional.len() < 2 {
+        panic!("Usage: dataset_diff <old dataset or data dir> <new dataset> [--details] [--delta out.delta]");
+    }
+    let old = read_dataset(&positional[0]);
+    let new = read_dataset(&positional[1]);
+    println!("Old: {} systems, new: {} systems", old.len(), new.len());
+    let diff = diff_datasets(old, new);
+
+    let limit = if details { usize::MAX } else { SUMMARY_EXAMPLES };
+    let new_neutrons = diff.new_neutron_stars();
+    let lost_neutrons = diff.lost_neutron_stars();
+    println!("Unchanged: {}", diff.unchanged);
+    println!("Added: {}", diff.added.len());
+    for record in diff.added.iter().take(limit) {
+        println!("  + {}", describe(record));
+    }
+    println!("Removed: {}", diff.removed.len());
+    for record in diff.removed.iter().take(limit) {
+        println!("  - {}", describe(record));
+    }
+    println!("Coordinate corrections: {}", diff.moved.len());
+    for (old, new) in diff.moved.iter().take(limit) {
+        println!("  ~ {} moved {} ly to ({}, {}, {})", describe(old), coordinate_shift(old, new), new.Coord_X, new.Coord_Y, new.Coord_Z);
+    }
+    println!("Star type reclassifications: {}", diff.reclassified.len());
+    for (old, new) in diff.reclassified.iter().take(limit) {
+        println!("  ~ {}: {} -> {}", old.name, old.mainStarType, new.mainStarType);
+    }
+    println!("Renamed: {}", diff.renamed.len());
+    for (old, new) in diff.renamed.iter().take(limit) {
+        println!("  ~ {} -> {}", old.name, new.name);
+    }
+    println!("New neutron stars: {}", new_neutrons.len());
+    for record in new_neutrons.iter().take(limit) {
+        println!("  * {}", describe(record));
+    }
+    println!("Lost neutron stars: {}", lost_neutrons.len());
+    for record in lost_neutrons.iter().take(limit) {
+        println!("  * {}", describe(record));
+    }
+
+    if let Some(delta_file) = delta_file {
+        let delta = diff.to_delta();
+        write_delta(&delta, &delta_file).unwrap();
+        println!("Wrote {} upserts and {} removals to {}", delta.upserts.len(), delta.removals.len(), delta_file);
+    }
+}
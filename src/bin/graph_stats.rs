@@ -0,0 +1,54 @@
+use std::env;
+use neutron_route_finder::{graph_stats, open_data_dir, write_outliers_csv, Systems};
+
+const TOP_COUNT: usize = 10;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        panic!("Usage: graph_stats <data dir> [outliers csv]");
+    }
+    let (manifest, systems, neutrons) = open_data_dir(&args[1]).unwrap();
+    let stats = graph_stats(&systems, &neutrons);
+
+    println!("Neutron stars: {} (pruning {}, jump range {} ly)", stats.len(), manifest.pruning, manifest.max_jump_distance);
+    println!("Edges: {}, average degree {:.2}", stats.edge_count, stats.average_degree());
+    println!("Edge length: average {:.1} ly, longest {:.1} ly", stats.average_edge_length(), stats.edge_length_max);
+
+    println!("\nDegree distribution:");
+    let histogram = stats.degree_histogram();
+    let mut lo = 0;
+    while lo < histogram.len() {
+        // 0, 1, 2-3, 4-7, ...
+        let hi = (lo * 2).max(lo + 1).min(histogram.len());
+        let count: usize = histogram[lo..hi].iter().sum();
+        let label = if hi - lo == 1 { lo.to_string() } else { format!("{}-{}", lo, hi - 1) };
+        println!("  {:>9} {:>10}", label, count);
+        lo = hi;
+    }
+
+    println!("\nConnected components: {}", stats.component_sizes.len());
+    for (i, size) in stats.component_sizes.iter().take(TOP_COUNT).enumerate() {
+        println!("  #{} {} stars", i, size);
+    }
+    let isolated = stats.isolated();
+    println!("Isolated neutron stars: {}", isolated.len());
+    for &n in isolated.iter().take(TOP_COUNT) {
+        println!("  {}", systems.name(neutrons.idx(n)));
+    }
+
+    println!("\nLargest gaps to the nearest neighbor:");
+    for (n, gap) in stats.largest_gaps(TOP_COUNT) {
+        println!("  {:>8.1} ly {}", gap, systems.name(neutrons.idx(n)));
+    }
+
+    println!("\nDensest {}ly regions (of {}):", neutron_route_finder::graph_stats::REGION_SIZE, stats.regions.len());
+    for region in stats.regions.iter().take(TOP_COUNT) {
+        println!("  {:?} {:>8} stars, {:.3} per 1000 ly cube, average degree {:.2}", region.key, region.neutron_count, region.density(), region.average_degree());
+    }
+
+    if let Some(outliers_file) = args.get(2) {
+        let written = write_outliers_csv(&stats, &systems, &neutrons, TOP_COUNT * 10, outliers_file).unwrap();
+        println!("\nWrote {} outliers to {}", written, outliers_file);
+    }
+}
@@ -0,0 +1,17 @@
+use std::env;
+use neutron_route_finder::{duplicate_id64, read_star_systems, import_star_systems_sqlite};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("Need input and output");
+    }
+    let input_file = &args[1];
+    let output_file = &args[2];
+    let systems = read_star_systems(input_file, |_| true);
+    println!("Read {} systems", systems.len());
+    if let Some(id64) = duplicate_id64(systems.iter().map(|s| s.id64).collect()) {
+        panic!("Several systems share id64 {}", id64);
+    }
+    import_star_systems_sqlite(&systems, output_file).unwrap();
+}
@@ -0,0 +1,32 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+// Local stand-in for a live event feed: replays a journal file, one event per
+// line, to every client that connects.
+// Usage: journal_feed <journal file> <listen address> [delay ms between events]
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("Need a journal file and a listen address");
+    }
+    let journal_file = args[1].clone();
+    let delay = std::time::Duration::from_millis(args.get(3).map(|a| a.parse().unwrap()).unwrap_or(0));
+    let listener = TcpListener::bind(&args[2]).unwrap();
+    println!("Serving {} on {}", journal_file, listener.local_addr().unwrap());
+    for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let journal_file = journal_file.clone();
+        std::thread::spawn(move || {
+            let f = BufReader::new(File::open(&journal_file).unwrap());
+            for line in f.lines() {
+                let line = line.unwrap();
+                if writeln!(stream, "{}", line).is_err() {
+                    return;
+                }
+                std::thread::sleep(delay);
+            }
+        });
+    }
+}
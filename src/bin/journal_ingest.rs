@@ -0,0 +1,63 @@
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::TcpStream;
+use neutron_route_finder::{JournalIngestor, ingest_journal};
+
+fn print_batch(path: &str, count: usize) {
+    println!("Wrote {} systems to {}", count, path);
+}
+
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+fn print_summary(ingestor: &JournalIngestor) {
+    println!("Saw {} events, skipped {}, {} systems still waiting for position or star type, {} dropped after waiting too long", ingestor.events_seen, ingestor.events_skipped, ingestor.pending_len(), ingestor.pending_dropped);
+}
+
+// Usage: journal_ingest <tcp://host:port | journal files...> <output dir> [batch size] [connection attempts]
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("Need a source and an output directory");
+    }
+    let batch_size: usize = args.get(3).map(|a| a.parse().unwrap()).unwrap_or(1000);
+    // How many times in a row a TCP feed may fail to connect before giving up
+    let max_attempts: usize = args.get(4).map(|a| a.parse().unwrap()).unwrap_or(12);
+    let source = &args[1];
+    let output_dir = &args[2];
+    std::fs::create_dir_all(output_dir).unwrap();
+    let mut ingestor = JournalIngestor::new();
+    if let Some(addr) = source.strip_prefix("tcp://") {
+        // Reconnect whenever the feed closes, until it can't be reached
+        // `max_attempts` times in a row
+        let mut failed_attempts = 0;
+        while failed_attempts < max_attempts {
+            match TcpStream::connect(addr) {
+                Ok(stream) => {
+                    failed_attempts = 0;
+                    println!("Connected to {}", addr);
+                    // A dropped connection is treated like the feed closing
+                    match ingest_journal(BufReader::new(stream), &mut ingestor, output_dir, batch_size, print_batch) {
+                        Ok(_) => println!("Feed closed"),
+                        Err(e) => println!("Feed failed: {}", e),
+                    }
+                    print_summary(&ingestor);
+                }
+                Err(e) => {
+                    failed_attempts += 1;
+                    println!("Couldn't connect to {} ({} of {} attempts): {}", addr, failed_attempts, max_attempts, e);
+                }
+            }
+            if failed_attempts < max_attempts {
+                std::thread::sleep(RECONNECT_DELAY);
+            }
+        }
+        println!("Giving up on {}", addr);
+    } else {
+        for path in source.split(',') {
+            let f = File::open(path).unwrap();
+            ingest_journal(BufReader::new(f), &mut ingestor, output_dir, batch_size, print_batch).unwrap();
+        }
+    }
+    print_summary(&ingestor);
+}
@@ -0,0 +1,49 @@
+use std::env;
+use neutron_route_finder::{open_data_dir, pruning_report, system_distance, Float, PruningPolicy, Systems};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("Usage: pruning_report <data dir> <scratch dir> [route count] [rule...]");
+    }
+    let data_dir = &args[1];
+    let scratch_dir = &args[2];
+    let route_count: usize = args.get(3).map(|a| a.parse().unwrap()).unwrap_or(20);
+    let rules: Vec<String> = if args.len() > 4 {
+        args[4..].to_vec()
+    } else {
+        ["occlusion", "octant:2", "yao:16", "theta:16", "rng"].iter().map(|r| r.to_string()).collect()
+    };
+    let (manifest, systems, _) = open_data_dir(data_dir).unwrap();
+    let jump_distance = Float::from(63.0);
+    manifest.check_jump_distance(jump_distance).unwrap();
+    let policies: Vec<PruningPolicy> = rules.iter().map(|r| PruningPolicy {
+        rule: r.parse().unwrap(),
+        max_distance: manifest.max_neighbor_distance,
+    }).collect();
+
+    // Fixed seed so reports for different datasets or builds are comparable
+    let mut seed: u64 = 0x5eed;
+    let mut next_idx = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((seed >> 33) % systems.len() as u64) as u32
+    };
+    let max_route_distance = Float::from(5000.0);
+    let mut pairs = Vec::new();
+    for _ in 0..route_count * 1000 {
+        if pairs.len() == route_count {
+            break;
+        }
+        let (start, goal) = (next_idx(), next_idx());
+        if start != goal && system_distance(&systems, start, goal) <= max_route_distance {
+            pairs.push((start, goal));
+        }
+    }
+
+    let rows = pruning_report(&systems, &policies, &pairs, manifest.max_jump_distance, jump_distance, scratch_dir).unwrap();
+    let neutron_count = (0..systems.len()).filter(|&idx| systems.is_neutron(idx)).count();
+    println!("{:<32} {:>10} {:>8} {:>8} {:>12} {:>9} {:>9} {:>8} {:>8} {:>8}", "policy", "edges", "avg deg", "max deg", "file bytes", "build s", "search s", "routes", "extra %", "worst");
+    for row in rows {
+        println!("{:<32} {:>10} {:>8.2} {:>8} {:>12} {:>9.2} {:>9.2} {:>8} {:>8.2} {:>8}", row.policy.to_string(), row.edges, row.average_degree(neutron_count), row.max_degree, row.file_size, row.build_secs, row.search_secs, format!("{}/{}", row.routes_found, pairs.len()), row.extra_jumps_percent(), row.worst_extra_jumps);
+    }
+}
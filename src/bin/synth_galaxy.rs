@@ -0,0 +1,50 @@
+use std::env;
+use neutron_route_finder::SyntheticGalaxy;
+use neutron_route_finder::pipeline::write_star_system_records;
+
+// "name=weight,name=weight,..."
+fn parse_weights(s: &str) -> Vec<(String, f64)> {
+    s.split(',').map(|pair| {
+        let (name, weight) = pair.rsplit_once('=').unwrap_or_else(|| panic!("expected name=weight, got {:?}", pair));
+        (name.trim().to_string(), weight.parse().unwrap())
+    }).collect()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut outputs = Vec::new();
+    let mut galaxy = SyntheticGalaxy::default();
+    let mut star_classes = Vec::new();
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        let mut value = || args_iter.next().unwrap_or_else(|| panic!("{} needs a value", arg)).clone();
+        match arg.as_str() {
+            "--seed" => galaxy.seed = value().parse().unwrap(),
+            "--systems" => galaxy.system_count = value().parse().unwrap(),
+            "--radius" => galaxy.radius = value().parse().unwrap(),
+            "--center" => {
+                let coords: Vec<f32> = value().split(',').map(|c| c.parse().unwrap()).collect();
+                galaxy.center = (coords[0], coords[1], coords[2]);
+            }
+            "--profiles" => galaxy.profiles = parse_weights(&value()).into_iter().map(|(name, weight)| (name.parse().unwrap(), weight)).collect(),
+            "--clusters" => galaxy.cluster_count = value().parse().unwrap(),
+            "--cluster-radius" => galaxy.cluster_radius = value().parse().unwrap(),
+            "--neutron-ratio" => galaxy.neutron_ratio = value().parse().unwrap(),
+            "--class" => star_classes.extend(parse_weights(&value())),
+            _ => outputs.push(arg.clone()),
+        }
+    }
+    if outputs.is_empty() {
+        panic!("Usage: synth_galaxy <output (csv, json lines, sqlite or bincode)>... [--seed N] [--systems N] [--radius LY] [--center X,Y,Z] [--profiles disk=W,core=W,halo=W,clusters=W] [--clusters N] [--cluster-radius LY] [--neutron-ratio R] [--class \"M (Red dwarf) Star=W\"]...");
+    }
+    if !star_classes.is_empty() {
+        galaxy.star_classes = star_classes;
+    }
+    let records = galaxy.generate();
+    let neutron_count = records.iter().filter(|r| r.mainStarType == "Neutron Star").count();
+    println!("Generated {} systems, {} neutron stars (seed {})", records.len(), neutron_count, galaxy.seed);
+    for output in &outputs {
+        write_star_system_records(&records, output).unwrap();
+        println!("Wrote {}", output);
+    }
+}
@@ -0,0 +1,89 @@
+use std::env;
+use neutron_route_finder::{find_systems_by_name, open_data_dir, validate_route, Float, FuelModel, Repair, ShipParams, Systems};
+
+// A route file lists one system per line, by name or by index into the cooked
+// systems. Blank lines and lines starting with # are skipped.
+fn read_route_file(filename: &str) -> Vec<String> {
+    std::fs::read_to_string(filename).unwrap().lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut positional = Vec::new();
+    let mut ship = ShipParams {
+        jump_distance: Float::from(63.0),
+        fuel: None,
+    };
+    let mut scoop = false;
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--jump-range" => ship.jump_distance = Float::from(args_iter.next().expect("--jump-range needs a value").parse::<f32>().unwrap()),
+            "--fuel" => {
+                let values: Vec<f32> = args_iter.next().expect("--fuel needs a value").split(',').map(|v| v.parse().unwrap()).collect();
+                let [tank, max_fuel_per_jump, mass, optimal_mass, linear_constant, power_constant] = values[..] else {
+                    panic!("--fuel needs TANK,MAX_PER_JUMP,MASS,OPTIMAL_MASS,LINEAR,POWER");
+                };
+                ship.fuel = Some(FuelModel { tank, max_fuel_per_jump, mass, optimal_mass, linear_constant, power_constant, scoop: false });
+            }
+            "--scoop" => scoop = true,
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if positional.len() < 2 {
+        panic!("Usage: validate_route <data dir> <route file> [--jump-range N] [--fuel TANK,MAX_PER_JUMP,MASS,OPTIMAL_MASS,LINEAR,POWER] [--scoop]");
+    }
+    if let Some(fuel) = ship.fuel.as_mut() {
+        fuel.scoop = scoop;
+    }
+    // Only the systems are needed, so any jump range goes: the neutron graph's
+    // cooked range doesn't matter here
+    let (_, systems, _) = open_data_dir(&positional[0]).unwrap();
+
+    let entries = read_route_file(&positional[1]);
+    let names: Vec<&str> = entries.iter().filter(|e| e.parse::<u32>().is_err()).map(|e| e.as_str()).collect();
+    let mut found = find_systems_by_name(&systems, &names).into_iter();
+    let mut route = Vec::new();
+    for entry in &entries {
+        let idx = match entry.parse::<u32>() {
+            Ok(idx) if idx < systems.len() => Some(idx),
+            Ok(_) => None,
+            Err(_) => found.next().unwrap(),
+        };
+        match idx {
+            Some(idx) => route.push(idx),
+            None => {
+                println!("INVALID: unknown system {:?} at position {}", entry, route.len());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let check = validate_route(&systems, &route, &ship);
+    for hop in &check.hops {
+        let boost = if hop.boost > 1.0 { format!(" (x{} boost)", hop.boost) } else { String::new() };
+        let fuel = match (hop.fuel_used, hop.fuel_left) {
+            (Some(used), Some(left)) => format!(", {:.2} t fuel, {:.2} t left{}", used, left, if hop.refuelled { " after scooping" } else { "" }),
+            _ => String::new(),
+        };
+        println!("{} -> {}: {:.2} ly{}{}", systems.name(hop.from), systems.name(hop.to), hop.distance, boost, fuel);
+    }
+    let Some(invalid) = check.invalid else {
+        println!("Valid: {} jumps", check.hops.len());
+        return;
+    };
+    println!("INVALID hop {}: {} -> {} {}", invalid.hop + 1, systems.name(route[invalid.hop]), systems.name(route[invalid.hop + 1]), invalid.problem);
+    match invalid.repair {
+        Some(Repair::Remove { position }) => println!("Suggested repair: remove {} at position {}", systems.name(route[position]), position),
+        Some(Repair::Insert { position, systems: inserted }) => {
+            let names: Vec<&str> = inserted.iter().map(|&idx| systems.name(idx)).collect();
+            println!("Suggested repair: insert {} between {} and {}", names.join(", "), systems.name(route[position - 1]), systems.name(route[position]));
+        }
+        None => println!("No repair found"),
+    }
+    std::process::exit(1);
+}
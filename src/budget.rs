@@ -0,0 +1,134 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use crate::{Progress, SearchMode, SilentProgress};
+
+// Shared flag for stopping a search from another thread. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Limits on a search. The defaults run it to completion.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    pub cancel: Option<CancelToken>,
+    pub deadline: Option<Instant>,
+    // Systems and neutron stars expanded, as in SearchCounters. The searches
+    // neutron_a_star runs between neutron stars count against the same cap.
+    pub max_expansions: Option<u64>,
+    // Bytes of search state (scores, parents and the queue) any one search may
+    // hold, estimated from the entries in them
+    pub max_memory: Option<usize>,
+    // None reports nothing
+    pub progress: Option<Arc<dyn Progress>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Cancelled,
+    Deadline,
+    ExpansionLimit,
+    MemoryLimit,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Cancelled => write!(f, "cancelled"),
+            StopReason::Deadline => write!(f, "out of time"),
+            StopReason::ExpansionLimit => write!(f, "expansion limit reached"),
+            StopReason::MemoryLimit => write!(f, "memory limit reached"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchOutcome {
+    Found(Vec<u32>),
+    NoRoute,
+    // Stopped before finishing. `best` is the shortest complete route seen so
+    // far, which may not be the one a full search would return.
+    Stopped { reason: StopReason, best: Option<Vec<u32>> },
+}
+
+impl SearchOutcome {
+    // The route found, or the best one so far if the search was stopped
+    pub fn route(self) -> Option<Vec<u32>> {
+        match self {
+            SearchOutcome::Found(route) => Some(route),
+            SearchOutcome::NoRoute => None,
+            SearchOutcome::Stopped { best, .. } => best,
+        }
+    }
+}
+
+// Expansions between progress updates
+const PROGRESS_INTERVAL: u64 = 1024;
+
+// What's left of a search's options, shared by the searches nested in it
+pub(crate) struct Budget<'a> {
+    options: &'a SearchOptions,
+    expansions: u64,
+    progress: &'a dyn Progress,
+}
+
+impl<'a> Budget<'a> {
+    pub(crate) fn new(options: &'a SearchOptions) -> Budget<'a> {
+        Budget { options, expansions: 0, progress: options.progress.as_deref().unwrap_or(&SilentProgress) }
+    }
+
+    pub(crate) fn progress(&self) -> &'a dyn Progress {
+        self.progress
+    }
+
+    // Ends the progress task the search began
+    pub(crate) fn end(&self) {
+        self.progress.advance(self.expansions);
+        self.progress.end();
+    }
+
+    // Called before each expansion, with the size of the expanding search's state
+    pub(crate) fn spend(&mut self, state_bytes: usize) -> Result<(), StopReason> {
+        self.expansions += 1;
+        if self.expansions.is_multiple_of(PROGRESS_INTERVAL) {
+            self.progress.advance(self.expansions);
+        }
+        let options = self.options;
+        if options.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+            Err(StopReason::Cancelled)
+        } else if options.max_expansions.is_some_and(|max| self.expansions > max) {
+            Err(StopReason::ExpansionLimit)
+        } else if options.max_memory.is_some_and(|max| state_bytes > max) {
+            Err(StopReason::MemoryLimit)
+        } else if options.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            Err(StopReason::Deadline)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Rough footprint of a hash map's entries: the pair plus a control byte
+pub(crate) fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.len() * (size_of::<(K, V)>() + 1)
+}
+
+pub(crate) fn heap_bytes<T>(heap: &BinaryHeap<T>) -> usize {
+    heap.len() * size_of::<T>()
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use crate::{NeutronGrid, NeutronStarSystem, Progress, PruningPolicy, write_neighbor_list, write_neutron_sections};
+
+// Identifies what the chunks in a checkpoint directory were built from
+const CHECKPOINT_KEY_FILE: &str = "KEY";
+
+fn chunk_path(chunk_dir: &Path, chunk: usize) -> PathBuf {
+    chunk_dir.join(format!("chunk-{:06}.bin", chunk))
+}
+
+fn read_chunk(path: &Path) -> std::io::Result<Vec<NeutronStarSystem>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    bincode::decode_from_std_read(&mut reader, bincode::config::standard()).map_err(std::io::Error::other)
+}
+
+fn write_chunk(path: &Path, chunk: &[NeutronStarSystem]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    bincode::encode_into_std_write(chunk, &mut writer, bincode::config::standard()).map_err(std::io::Error::other)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    std::fs::rename(tmp_path, path)
+}
+
+// Builds the neutron graph into `output_filepath`, saving each finished chunk
+// under `chunk_dir` first. Rerunning with the same `key` (anything that
+// identifies the systems and build parameters) only builds the chunks that
+// are missing. The chunk directory is removed once the file is written.
+// Progress is counted in chunks. Returns the number of neutron stars.
+pub fn build_neutron_file_checkpointed(grid: &NeutronGrid, max_jump_distance: f32, policy: &PruningPolicy, key: &str, chunk_dir: &str, output_filepath: &str, progress: &dyn Progress) -> std::io::Result<usize> {
+    let chunk_dir = Path::new(chunk_dir);
+    std::fs::create_dir_all(chunk_dir)?;
+    let key_path = chunk_dir.join(CHECKPOINT_KEY_FILE);
+    if std::fs::read_to_string(&key_path).ok().as_deref() != Some(key) {
+        // Chunks from another dataset or parameters can't be reused
+        std::fs::remove_dir_all(chunk_dir)?;
+        std::fs::create_dir_all(chunk_dir)?;
+        std::fs::write(&key_path, key)?;
+    }
+
+    let chunk_count = grid.chunk_count();
+    let resumed = (0..chunk_count).filter(|&chunk| chunk_path(chunk_dir, chunk).exists()).count();
+    progress.begin("Neutron graph", Some(chunk_count as u64));
+    if resumed > 0 {
+        progress.note(&format!("Resuming from checkpoint: {} of {} chunks already built", resumed, chunk_count));
+        progress.advance(resumed as u64);
+    }
+    let mut built = 0;
+    grid.build_chunks_with_policy(max_jump_distance, policy, |chunk| chunk_path(chunk_dir, chunk).exists(), |chunk, neutrons| {
+        write_chunk(&chunk_path(chunk_dir, chunk), &neutrons)?;
+        built += 1;
+        progress.advance((resumed + built) as u64);
+        Ok::<(), std::io::Error>(())
+    })?;
+    progress.end();
+
+    let count = write_neutron_file_from_chunks(chunk_dir, chunk_count, output_filepath)?;
+    if count != grid.len() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("checkpoint chunks hold {} neutron stars, expected {}", count, grid.len())));
+    }
+    std::fs::remove_dir_all(chunk_dir)?;
+    Ok(count)
+}
+
+// Same layout as `write_neutron_file`, streamed from the chunk files in two
+// passes so the whole graph never has to be in memory.
+fn write_neutron_file_from_chunks(chunk_dir: &Path, chunk_count: usize, output_filepath: &str) -> std::io::Result<usize> {
+    let mut stars: Vec<(usize, u64, u32)> = Vec::new();
+    for chunk in 0..chunk_count {
+        stars.extend(read_chunk(&chunk_path(chunk_dir, chunk))?.iter().map(|n| (n.neighbors.len(), n.id64, n.idx)));
+    }
+    write_neutron_sections(output_filepath, stars.iter().copied(), |out| {
+        for chunk in 0..chunk_count {
+            for n in read_chunk(&chunk_path(chunk_dir, chunk))? {
+                write_neighbor_list(out, &n.neighbors)?;
+            }
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::Write;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use crate::{Float, StarSystem, Systems, V3};
+
+// Columnar systems file layout (little endian), sections naturally aligned so
+// the mmap can be queried in place:
+//   magic [u8; 8], count u64, names_len u64, star_types_len u64
+//   x [f32; count], y [f32; count], z [f32; count], distance_from_sol [f32; count]
+//   name_offsets [u64; count + 1]
+//   id64 [u64; count]
+//   id64_order [u32; count]  (system indexes sorted by id64, for lookups)
+//   star_type [u8; count]  (index into the star type table)
+//   names [u8; names_len]
+//   star type table [u8; star_types_len]  ('\n' separated)
+const COLUMNAR_FILE_MAGIC: &[u8; 8] = b"NSCOLS02";
+const COLUMNAR_FILE_HEADER_SIZE: usize = 32;
+const NEUTRON_STAR_TYPE: &str = "Neutron Star";
+
+pub fn write_columnar_systems(systems: &[StarSystem], output_filepath: &str) -> std::io::Result<()> {
+    if systems.windows(2).any(|w| w[0].distance_from_sol > w[1].distance_from_sol) {
+        panic!("systems must be sorted by distance from Sol");
+    }
+    let mut star_types: Vec<&str> = Vec::new();
+    let mut star_type_idxs: Vec<u8> = Vec::with_capacity(systems.len());
+    for system in systems {
+        let type_idx = match star_types.iter().position(|&t| t == system.main_star_type) {
+            Some(type_idx) => type_idx,
+            None => {
+                if system.main_star_type.contains('\n') {
+                    panic!("star type {:?} contains a newline", system.main_star_type);
+                }
+                star_types.push(&system.main_star_type);
+                star_types.len() - 1
+            }
+        };
+        star_type_idxs.push(type_idx.try_into().expect("more than 256 distinct star types"));
+    }
+    let star_type_table = star_types.join("\n");
+    let names_len: u64 = systems.iter().map(|s| s.name.len() as u64).sum();
+
+    let out_f = File::create(output_filepath)?;
+    let mut buf_out = std::io::BufWriter::new(out_f);
+    buf_out.write_all(COLUMNAR_FILE_MAGIC)?;
+    buf_out.write_all(&(systems.len() as u64).to_le_bytes())?;
+    buf_out.write_all(&names_len.to_le_bytes())?;
+    buf_out.write_all(&(star_type_table.len() as u64).to_le_bytes())?;
+    for column in [|s: &StarSystem| s.coords.0, |s: &StarSystem| s.coords.1, |s: &StarSystem| s.coords.2, |s: &StarSystem| s.distance_from_sol] {
+        for system in systems {
+            buf_out.write_all(&f32::from(column(system)).to_le_bytes())?;
+        }
+    }
+    let mut offset: u64 = 0;
+    for system in systems {
+        buf_out.write_all(&offset.to_le_bytes())?;
+        offset += system.name.len() as u64;
+    }
+    buf_out.write_all(&offset.to_le_bytes())?;
+    for system in systems {
+        buf_out.write_all(&system.id64.to_le_bytes())?;
+    }
+    let mut id64_order: Vec<u32> = (0..systems.len() as u32).collect();
+    id64_order.par_sort_by_key(|&idx| systems[idx as usize].id64);
+    for idx in id64_order {
+        buf_out.write_all(&idx.to_le_bytes())?;
+    }
+    buf_out.write_all(&star_type_idxs)?;
+    for system in systems {
+        buf_out.write_all(system.name.as_bytes())?;
+    }
+    buf_out.write_all(star_type_table.as_bytes())?;
+    buf_out.flush()
+}
+
+pub struct ColumnarSystems {
+    map: Mmap,
+    count: usize,
+    name_offsets_start: usize,
+    id64_start: usize,
+    id64_order_start: usize,
+    star_type_start: usize,
+    names_start: usize,
+    star_types: Vec<String>,
+    neutron_type: Option<u8>,
+}
+
+impl ColumnarSystems {
+    pub fn new(filepath: &str) -> Self {
+        if cfg!(target_endian = "big") {
+            panic!("columnar systems files are only readable in place on little endian targets");
+        }
+        let file = File::open(filepath).unwrap();
+        let map = unsafe { Mmap::map(&file).unwrap() };
+        if map.len() < COLUMNAR_FILE_HEADER_SIZE || &map[..8] != COLUMNAR_FILE_MAGIC {
+            panic!("{} is not a columnar systems file", filepath);
+        }
+        let read_u64 = |at: usize| u64::from_le_bytes(map[at..at+8].try_into().unwrap()) as usize;
+        let count = read_u64(8);
+        let names_len = read_u64(16);
+        let star_types_len = read_u64(24);
+        let sections = (|| {
+            let name_offsets_start = count.checked_mul(16)?.checked_add(COLUMNAR_FILE_HEADER_SIZE)?;
+            let id64_start = count.checked_add(1)?.checked_mul(8)?.checked_add(name_offsets_start)?;
+            let id64_order_start = count.checked_mul(8)?.checked_add(id64_start)?;
+            let star_type_start = count.checked_mul(4)?.checked_add(id64_order_start)?;
+            let names_start = star_type_start.checked_add(count)?;
+            let star_types_start = names_start.checked_add(names_len)?;
+            let end = star_types_start.checked_add(star_types_len)?;
+            Some((name_offsets_start, id64_start, id64_order_start, star_type_start, names_start, star_types_start, end))
+        })();
+        let Some((name_offsets_start, id64_start, id64_order_start, star_type_start, names_start, star_types_start, end)) = sections else {
+            panic!("{} is corrupt: {} systems don't fit in a file", filepath, count);
+        };
+        if map.len() != end {
+            panic!("{} is truncated or corrupt", filepath);
+        }
+        assert_eq!(map.as_ptr().align_offset(8), 0);
+        let Ok(star_type_table) = std::str::from_utf8(&map[star_types_start..]) else {
+            panic!("{} is corrupt: star types aren't UTF-8", filepath);
+        };
+        let star_types: Vec<String> = if star_type_table.is_empty() {
+            Vec::new()
+        } else {
+            star_type_table.split('\n').map(|t| t.to_string()).collect()
+        };
+        let neutron_type = star_types.iter().position(|t| t == NEUTRON_STAR_TYPE).map(|t| t as u8);
+        map.advise(memmap2::Advice::Random).unwrap();
+        let systems = Self {
+            map,
+            count,
+            name_offsets_start,
+            id64_start,
+            id64_order_start,
+            star_type_start,
+            names_start,
+            star_types,
+            neutron_type,
+        };
+        // Checked once here so name() can skip it on every lookup
+        let offsets = systems.name_offsets();
+        if offsets[0] != 0 || offsets[count] != names_len as u64 || offsets.windows(2).any(|w| w[0] > w[1]) {
+            panic!("{} is corrupt: name offsets are out of order", filepath);
+        }
+        let Ok(names) = std::str::from_utf8(&systems.map[names_start..star_types_start]) else {
+            panic!("{} is corrupt: names aren't UTF-8", filepath);
+        };
+        if !offsets.par_iter().all(|&offset| names.is_char_boundary(offset as usize)) {
+            panic!("{} is corrupt: a name offset splits a character", filepath);
+        }
+        // Likewise main_star_type() and find_by_id64() index with these unchecked
+        let type_count = systems.star_types.len();
+        if !systems.map[star_type_start..names_start].par_iter().all(|&t| (t as usize) < type_count) {
+            panic!("{} is corrupt: a star type is out of range", filepath);
+        }
+        if !systems.id64_order().par_iter().all(|&idx| (idx as usize) < count) {
+            panic!("{} is corrupt: an id64 order entry is out of range", filepath);
+        }
+        systems
+    }
+
+    fn f32_column(&self, column: usize) -> &[f32] {
+        let start = COLUMNAR_FILE_HEADER_SIZE + column * self.count * 4;
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(start) as *const f32, self.count) }
+    }
+
+    fn name_offsets(&self) -> &[u64] {
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(self.name_offsets_start) as *const u64, self.count + 1) }
+    }
+
+    fn id64s(&self) -> &[u64] {
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(self.id64_start) as *const u64, self.count) }
+    }
+
+    fn id64_order(&self) -> &[u32] {
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(self.id64_order_start) as *const u32, self.count) }
+    }
+
+    fn star_type_idx(&self, idx: u32) -> u8 {
+        self.map[self.star_type_start + idx as usize]
+    }
+
+    pub fn get(&self, idx: u32) -> StarSystem {
+        StarSystem {
+            name: self.name(idx).to_string(),
+            main_star_type: self.main_star_type(idx).to_string(),
+            coords: self.coords(idx),
+            distance_from_sol: self.distance_from_sol(idx),
+            is_neutron: self.is_neutron(idx),
+            id64: self.id64(idx),
+        }
+    }
+
+    pub fn star_types(&self) -> &[String] {
+        &self.star_types
+    }
+}
+
+impl Systems for ColumnarSystems {
+    fn len(&self) -> u32 {
+        self.count as u32
+    }
+
+    fn coords(&self, idx: u32) -> V3 {
+        let idx = idx as usize;
+        (
+            self.f32_column(0)[idx].into(),
+            self.f32_column(1)[idx].into(),
+            self.f32_column(2)[idx].into(),
+        )
+    }
+
+    fn distance_from_sol(&self, idx: u32) -> Float {
+        self.f32_column(3)[idx as usize].into()
+    }
+
+    fn is_neutron(&self, idx: u32) -> bool {
+        self.neutron_type == Some(self.star_type_idx(idx))
+    }
+
+    fn name(&self, idx: u32) -> &str {
+        let offsets = self.name_offsets();
+        let start = self.names_start + offsets[idx as usize] as usize;
+        let end = self.names_start + offsets[idx as usize + 1] as usize;
+        // new() checked the names are UTF-8 and every offset is on a character boundary
+        unsafe { std::str::from_utf8_unchecked(&self.map[start..end]) }
+    }
+
+    fn main_star_type(&self, idx: u32) -> &str {
+        &self.star_types[self.star_type_idx(idx) as usize]
+    }
+
+    fn id64(&self, idx: u32) -> u64 {
+        self.id64s()[idx as usize]
+    }
+
+    fn find_by_id64(&self, id64: u64) -> Option<u32> {
+        let id64s = self.id64s();
+        let order = self.id64_order();
+        let pos = order.partition_point(|&idx| id64s[idx as usize] < id64);
+        order.get(pos).copied().filter(|&idx| id64s[idx as usize] == id64)
+    }
+}
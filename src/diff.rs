@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use crate::{sort_star_system_records, DatasetDelta, StarSystemRecord};
+
+const NEUTRON_STAR_TYPE: &str = "Neutron Star";
+
+fn record_id64(record: &StarSystemRecord) -> u64 {
+    record.system_id64()
+}
+
+fn is_neutron(record: &StarSystemRecord) -> bool {
+    record.mainStarType == NEUTRON_STAR_TYPE
+}
+
+// Systems present in both datasets are matched by id64. Records without one
+// are matched by name, and by position only among systems sharing a name. A
+// matched system can show up under several kinds of change.
+#[derive(Debug, Default)]
+pub struct DatasetDiff {
+    pub added: Vec<StarSystemRecord>,
+    pub removed: Vec<StarSystemRecord>,
+    // (old, new) pairs
+    pub moved: Vec<(StarSystemRecord, StarSystemRecord)>,
+    pub reclassified: Vec<(StarSystemRecord, StarSystemRecord)>,
+    pub renamed: Vec<(StarSystemRecord, StarSystemRecord)>,
+    pub unchanged: usize,
+}
+
+pub fn coordinate_shift(old: &StarSystemRecord, new: &StarSystemRecord) -> f32 {
+    let (dx, dy, dz) = (new.Coord_X - old.Coord_X, new.Coord_Y - old.Coord_Y, new.Coord_Z - old.Coord_Z);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+impl DatasetDiff {
+    pub fn changed(&self) -> usize {
+        let mut changed: Vec<u64> = self.moved.iter().chain(&self.reclassified).chain(&self.renamed).map(|(_, new)| record_id64(new)).collect();
+        changed.sort();
+        changed.dedup();
+        changed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed() == 0
+    }
+
+    // Neutron stars that are new to the dataset, including reclassifications
+    pub fn new_neutron_stars(&self) -> Vec<&StarSystemRecord> {
+        self.added.iter().filter(|r| is_neutron(r))
+            .chain(self.reclassified.iter().filter(|(old, new)| !is_neutron(old) && is_neutron(new)).map(|(_, new)| new))
+            .collect()
+    }
+
+    // Neutron stars that are gone, including reclassifications
+    pub fn lost_neutron_stars(&self) -> Vec<&StarSystemRecord> {
+        self.removed.iter().filter(|r| is_neutron(r))
+            .chain(self.reclassified.iter().filter(|(old, new)| is_neutron(old) && !is_neutron(new)).map(|(old, _)| old))
+            .collect()
+    }
+
+    // The changes as a delta for `apply_delta`/`update_data_dir`
+    pub fn to_delta(&self) -> DatasetDelta {
+        let mut upserts: HashMap<u64, &StarSystemRecord> = HashMap::new();
+        for (old, new) in self.moved.iter().chain(&self.reclassified).chain(&self.renamed) {
+            upserts.insert(record_id64(old), new);
+        }
+        let mut upserts: Vec<StarSystemRecord> = self.added.iter().map(|r| (record_id64(r), r)).chain(upserts).map(|(id64, r)| {
+            // Pinned to the old record's id64, so a system that was renamed or
+            // (without an id64 of its own) moved still replaces its old record
+            let mut r = r.clone();
+            r.id64 = Some(id64);
+            r
+        }).collect();
+        sort_star_system_records(&mut upserts);
+        DatasetDelta {
+            upserts,
+            removals: self.removed.iter().map(record_id64).collect(),
+        }
+    }
+}
+
+fn same_position(a: &StarSystemRecord, b: &StarSystemRecord) -> bool {
+    (a.Coord_X, a.Coord_Y, a.Coord_Z) == (b.Coord_X, b.Coord_Y, b.Coord_Z)
+}
+
+fn compare(diff: &mut DatasetDiff, old: StarSystemRecord, new: StarSystemRecord) {
+    let moved = !same_position(&old, &new);
+    let reclassified = old.mainStarType != new.mainStarType;
+    let renamed = old.name != new.name;
+    if !(moved || reclassified || renamed) {
+        diff.unchanged += 1;
+        return;
+    }
+    let pair = || (old.clone(), new.clone());
+    if moved {
+        diff.moved.push(pair());
+    }
+    if reclassified {
+        diff.reclassified.push(pair());
+    }
+    if renamed {
+        diff.renamed.push(pair());
+    }
+}
+
+pub fn diff_datasets(old: Vec<StarSystemRecord>, new: Vec<StarSystemRecord>) -> DatasetDiff {
+    let mut diff = DatasetDiff::default();
+    let (old_keyed, old_unkeyed): (Vec<StarSystemRecord>, Vec<StarSystemRecord>) = old.into_iter().partition(|r| r.id64.is_some());
+    let mut old_by_id64: HashMap<u64, StarSystemRecord> = old_keyed.into_iter().map(|r| (record_id64(&r), r)).collect();
+    let mut old_by_name: HashMap<String, Vec<StarSystemRecord>> = HashMap::new();
+    for r in old_unkeyed {
+        old_by_name.entry(r.name.clone()).or_default().push(r);
+    }
+
+    // Systems without an id64 that kept their position are paired off first,
+    // so a moved one can't claim the record of a namesake that stayed put
+    let mut unmatched = Vec::new();
+    for new in new {
+        if new.id64.is_some() {
+            match old_by_id64.remove(&record_id64(&new)) {
+                Some(old) => compare(&mut diff, old, new),
+                None => diff.added.push(new),
+            }
+            continue;
+        }
+        let same_name = old_by_name.get_mut(&new.name);
+        match same_name.and_then(|olds| olds.iter().position(|old| same_position(old, &new)).map(|i| olds.swap_remove(i))) {
+            Some(old) => compare(&mut diff, old, new),
+            None => unmatched.push(new),
+        }
+    }
+    // The rest are matched to the nearest system left with their name
+    for new in unmatched {
+        let nearest = old_by_name.get_mut(&new.name).and_then(|olds| {
+            let i = (0..olds.len()).min_by(|&a, &b| coordinate_shift(&olds[a], &new).total_cmp(&coordinate_shift(&olds[b], &new)))?;
+            Some(olds.swap_remove(i))
+        });
+        match nearest {
+            Some(old) => compare(&mut diff, old, new),
+            None => diff.added.push(new),
+        }
+    }
+
+    diff.removed = old_by_id64.into_values().chain(old_by_name.into_values().flatten()).collect();
+    sort_star_system_records(&mut diff.removed);
+    diff.moved.sort_by(|a, b| coordinate_shift(&b.0, &b.1).total_cmp(&coordinate_shift(&a.0, &a.1)));
+    diff
+}
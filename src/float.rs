@@ -0,0 +1,150 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// Precision of the search math, picked with the "f64" cargo feature.
+// Coordinates are stored as f32 either way.
+#[cfg(not(feature = "f64"))]
+pub type FloatInner = f32;
+#[cfg(feature = "f64")]
+pub type FloatInner = f64;
+
+// Plain IEEE arithmetic: no fast-math flags, no fused multiply-adds, and only
+// correctly rounded operations (+ - * / sqrt) in distance and range checks,
+// so the same inputs give bit-identical decisions on every build and
+// platform. Ordering is IEEE totalOrder, so comparisons never panic: NaNs
+// sort above everything, or below everything if their sign bit is set, and
+// -0.0 sorts below 0.0.
+#[derive(Clone, Copy, Default)]
+pub struct Float(pub FloatInner);
+
+impl Float {
+    pub fn sqrt(self) -> Self {
+        Float(self.0.sqrt())
+    }
+
+    pub fn ceil(self) -> Self {
+        Float(self.0.ceil())
+    }
+
+    pub fn floor(self) -> Self {
+        Float(self.0.floor())
+    }
+
+    pub fn abs(self) -> Self {
+        Float(self.0.abs())
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        Float(self.0.max(other.0))
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        Float(self.0.min(other.0))
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.0.is_nan()
+    }
+}
+
+// Some of these are no-ops, depending on the precision
+impl From<f32> for Float {
+    #[allow(clippy::useless_conversion)]
+    fn from(f: f32) -> Float {
+        Float(f.into())
+    }
+}
+
+impl From<Float> for f32 {
+    #[allow(clippy::unnecessary_cast)]
+    fn from(f: Float) -> f32 {
+        f.0 as f32
+    }
+}
+
+impl From<Float> for f64 {
+    #[allow(clippy::useless_conversion)]
+    fn from(f: Float) -> f64 {
+        f.0.into()
+    }
+}
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Float {}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl fmt::Debug for Float {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Neg for Float {
+    type Output = Float;
+
+    fn neg(self) -> Float {
+        Float(-self.0)
+    }
+}
+
+macro_rules! impl_ops {
+    ($($op_trait:ident $op_fn:ident $assign_trait:ident $assign_fn:ident),*) => {$(
+        impl $op_trait for Float {
+            type Output = Float;
+
+            fn $op_fn(self, rhs: Float) -> Float {
+                Float(self.0.$op_fn(rhs.0))
+            }
+        }
+
+        impl $op_trait<FloatInner> for Float {
+            type Output = Float;
+
+            fn $op_fn(self, rhs: FloatInner) -> Float {
+                Float(self.0.$op_fn(rhs))
+            }
+        }
+
+        impl $assign_trait for Float {
+            fn $assign_fn(&mut self, rhs: Float) {
+                self.0.$assign_fn(rhs.0);
+            }
+        }
+
+        impl $assign_trait<FloatInner> for Float {
+            fn $assign_fn(&mut self, rhs: FloatInner) {
+                self.0.$assign_fn(rhs);
+            }
+        }
+    )*};
+}
+
+impl_ops! {
+    Add add AddAssign add_assign,
+    Sub sub SubAssign sub_assign,
+    Mul mul MulAssign mul_assign,
+    Div div DivAssign div_assign
+}
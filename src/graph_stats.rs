@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use rayon::prelude::*;
+use crate::{system_distance, NeutronFileMap, Systems};
+
+// Regions for density figures are cubes this many ly on a side
+pub const REGION_SIZE: f32 = 5000.0;
+
+#[derive(Debug, Clone)]
+pub struct RegionStats {
+    // Region corner in REGION_SIZE units
+    pub key: (i32, i32, i32),
+    pub neutron_count: usize,
+    pub edge_count: usize,
+}
+
+impl RegionStats {
+    // Neutron stars per 1000 ly cube
+    pub fn density(&self) -> f64 {
+        self.neutron_count as f64 / (REGION_SIZE as f64 / 1000.0).powi(3)
+    }
+
+    pub fn average_degree(&self) -> f64 {
+        self.edge_count as f64 / self.neutron_count.max(1) as f64
+    }
+}
+
+// Everything is indexed by position in the neutron file
+#[derive(Debug, Clone)]
+pub struct GraphStats {
+    pub degree: Vec<u32>,
+    pub in_degree: Vec<u32>,
+    // Length of each star's shortest edge, None for stars without neighbors
+    pub nearest: Vec<Option<f32>>,
+    // Weakly connected component of each star, numbered largest first
+    pub component: Vec<u32>,
+    pub component_sizes: Vec<usize>,
+    pub edge_count: usize,
+    pub edge_length_total: f64,
+    pub edge_length_max: f32,
+    pub regions: Vec<RegionStats>,
+}
+
+fn find(parent: &mut [u32], mut x: u32) -> u32 {
+    while parent[x as usize] != x {
+        parent[x as usize] = parent[parent[x as usize] as usize];
+        x = parent[x as usize];
+    }
+    x
+}
+
+impl GraphStats {
+    pub fn len(&self) -> usize {
+        self.degree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.degree.is_empty()
+    }
+
+    // How many stars have each out-degree
+    pub fn degree_histogram(&self) -> Vec<usize> {
+        let max = self.degree.iter().copied().max().unwrap_or(0) as usize;
+        let mut histogram = vec![0; max + 1];
+        for &d in &self.degree {
+            histogram[d as usize] += 1;
+        }
+        histogram
+    }
+
+    pub fn average_degree(&self) -> f64 {
+        self.edge_count as f64 / self.len().max(1) as f64
+    }
+
+    pub fn average_edge_length(&self) -> f64 {
+        self.edge_length_total / self.edge_count.max(1) as f64
+    }
+
+    // Stars with no edges in either direction
+    pub fn isolated(&self) -> Vec<u32> {
+        (0..self.len() as u32).filter(|&n| self.degree[n as usize] == 0 && self.in_degree[n as usize] == 0).collect()
+    }
+
+    // The `count` stars farthest from their nearest neighbor, farthest first
+    pub fn largest_gaps(&self, count: usize) -> Vec<(u32, f32)> {
+        let mut gaps: Vec<(u32, f32)> = self.nearest.iter().enumerate().filter_map(|(n, d)| d.map(|d| (n as u32, d))).collect();
+        gaps.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        gaps.truncate(count);
+        gaps
+    }
+
+    // Stars worth a look, with the reason: isolated, outside the main
+    // component, unusually high degree or among the `gap_count` largest gaps
+    pub fn outliers(&self, gap_count: usize) -> Vec<(u32, &'static str)> {
+        let mean = self.average_degree();
+        let variance = self.degree.iter().map(|&d| (d as f64 - mean).powi(2)).sum::<f64>() / self.len().max(1) as f64;
+        let high_degree = mean + 3.0 * variance.sqrt();
+        let mut retval = Vec::new();
+        for n in 0..self.len() {
+            if self.degree[n] == 0 && self.in_degree[n] == 0 {
+                retval.push((n as u32, "isolated"));
+            } else if self.component[n] != 0 {
+                retval.push((n as u32, "disconnected"));
+            } else if self.degree[n] as f64 > high_degree {
+                retval.push((n as u32, "high degree"));
+            }
+        }
+        for (n, _) in self.largest_gaps(gap_count) {
+            retval.push((n, "large gap"));
+        }
+        retval
+    }
+}
+
+pub fn graph_stats<S: Systems>(systems: &S, neutrons: &NeutronFileMap) -> GraphStats {
+    let count = neutrons.len();
+    let per_star: Vec<(u32, Option<f32>, f64, f32)> = (0..count).into_par_iter().map(|n| {
+        let idx = neutrons.idx(n);
+        let lengths = neutrons.neighbors(n).iter().map(|&other| f32::from(system_distance(systems, idx, neutrons.idx(other))));
+        let (total, max, nearest) = lengths.fold((0.0f64, 0.0f32, None), |(total, max, nearest): (f64, f32, Option<f32>), d| {
+            (total + d as f64, max.max(d), Some(nearest.map_or(d, |n: f32| n.min(d))))
+        });
+        (neutrons.neighbors(n).len() as u32, nearest, total, max)
+    }).collect();
+
+    let mut in_degree = vec![0u32; count as usize];
+    let mut parent: Vec<u32> = (0..count).collect();
+    for n in 0..count {
+        for &other in neutrons.neighbors(n) {
+            in_degree[other as usize] += 1;
+            let (a, b) = (find(&mut parent, n), find(&mut parent, other));
+            if a != b {
+                parent[a.max(b) as usize] = a.min(b);
+            }
+        }
+    }
+    let roots: Vec<u32> = (0..count).map(|n| find(&mut parent, n)).collect();
+    let mut root_sizes: HashMap<u32, usize> = HashMap::new();
+    for &root in &roots {
+        *root_sizes.entry(root).or_default() += 1;
+    }
+    let mut by_size: Vec<(u32, usize)> = root_sizes.into_iter().collect();
+    by_size.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let component_number: HashMap<u32, u32> = by_size.iter().enumerate().map(|(i, &(root, _))| (root, i as u32)).collect();
+
+    let mut regions: HashMap<(i32, i32, i32), RegionStats> = HashMap::new();
+    for n in 0..count {
+        let (x, y, z) = systems.coords(neutrons.idx(n));
+        let key = |c: f32| (c / REGION_SIZE).floor() as i32;
+        let key = (key(x.into()), key(y.into()), key(z.into()));
+        let region = regions.entry(key).or_insert(RegionStats{key, neutron_count: 0, edge_count: 0});
+        region.neutron_count += 1;
+        region.edge_count += per_star[n as usize].0 as usize;
+    }
+    let mut regions: Vec<RegionStats> = regions.into_values().collect();
+    regions.sort_by(|a, b| b.neutron_count.cmp(&a.neutron_count).then(a.key.cmp(&b.key)));
+
+    GraphStats {
+        degree: per_star.iter().map(|s| s.0).collect(),
+        in_degree,
+        nearest: per_star.iter().map(|s| s.1).collect(),
+        component: roots.iter().map(|root| component_number[root]).collect(),
+        component_sizes: by_size.iter().map(|&(_, size)| size).collect(),
+        edge_count: per_star.iter().map(|s| s.0 as usize).sum(),
+        edge_length_total: per_star.iter().map(|s| s.2).sum(),
+        edge_length_max: per_star.iter().map(|s| s.3).fold(0.0, f32::max),
+        regions,
+    }
+}
+
+pub fn write_outliers_csv<S: Systems>(stats: &GraphStats, systems: &S, neutrons: &NeutronFileMap, gap_count: usize, output_filepath: &str) -> std::io::Result<usize> {
+    let mut writer = csv::Writer::from_path(output_filepath)?;
+    writer.write_record(["name", "id64", "x", "y", "z", "degree", "in_degree", "component", "component_size", "nearest_neighbor_ly", "reason"])?;
+    let outliers = stats.outliers(gap_count);
+    for &(n, reason) in &outliers {
+        let idx = neutrons.idx(n);
+        let (x, y, z) = systems.coords(idx);
+        let component = stats.component[n as usize];
+        writer.write_record([
+            systems.name(idx).to_string(),
+            systems.id64(idx).to_string(),
+            f32::from(x).to_string(),
+            f32::from(y).to_string(),
+            f32::from(z).to_string(),
+            stats.degree[n as usize].to_string(),
+            stats.in_degree[n as usize].to_string(),
+            component.to_string(),
+            stats.component_sizes[component as usize].to_string(),
+            stats.nearest[n as usize].map(|d| d.to_string()).unwrap_or_default(),
+            reason.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(outliers.len())
+}
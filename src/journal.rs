@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use crate::{DatasetDelta, StarSystemRecord, write_delta};
+
+// Player journal events (as written by the game or relayed through EDDN)
+// carry a system's position on FSDJump/Location/CarrierJump and its stars on
+// Scan. A system is ready for the dataset once both have been seen.
+#[derive(serde::Deserialize)]
+struct JournalEvent {
+    event: String,
+    #[serde(rename = "StarSystem")]
+    star_system: Option<String>,
+    #[serde(rename = "SystemAddress")]
+    system_address: Option<u64>,
+    #[serde(rename = "StarPos")]
+    star_pos: Option<[f32; 3]>,
+    #[serde(rename = "StarType")]
+    star_type: Option<String>,
+    #[serde(rename = "DistanceFromArrivalLS")]
+    distance_from_arrival_ls: Option<f64>,
+}
+
+// EDDN wraps journal events in an envelope
+#[derive(serde::Deserialize)]
+struct EddnEnvelope {
+    message: JournalEvent,
+}
+
+// Events are matched up by SystemAddress, or by name for events without one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PendingKey {
+    Id64(u64),
+    Name(String),
+}
+
+#[derive(Debug, Default)]
+struct PendingSystem {
+    name: String,
+    id64: Option<u64>,
+    coords: Option<[f32; 3]>,
+    main_star_type: Option<String>,
+    // events_seen when this was last updated
+    last_seen: usize,
+}
+
+pub fn journal_star_type_name(code: &str) -> String {
+    let name = match code {
+        "O" => "O (Blue-White) Star",
+        "B" => "B (Blue-White) Star",
+        "A" => "A (Blue-White) Star",
+        "F" => "F (White) Star",
+        "G" => "G (White-Yellow) Star",
+        "K" => "K (Yellow-Orange) Star",
+        "M" => "M (Red dwarf) Star",
+        "L" => "L (Brown dwarf) Star",
+        "T" => "T (Brown dwarf) Star",
+        "Y" => "Y (Brown dwarf) Star",
+        "TTS" => "T Tauri Star",
+        "AeBe" => "Herbig Ae/Be Star",
+        "W" => "Wolf-Rayet Star",
+        "WN" => "Wolf-Rayet N Star",
+        "WNC" => "Wolf-Rayet NC Star",
+        "WC" => "Wolf-Rayet C Star",
+        "WO" => "Wolf-Rayet O Star",
+        "CS" => "CS Star",
+        "C" => "C Star",
+        "CN" => "CN Star",
+        "CJ" => "CJ Star",
+        "MS" => "MS-type Star",
+        "S" => "S-type Star",
+        "N" => "Neutron Star",
+        "H" => "Black Hole",
+        "SupermassiveBlackHole" => "Supermassive Black Hole",
+        "A_BlueWhiteSuperGiant" => "A (Blue-White super giant) Star",
+        "B_BlueWhiteSuperGiant" => "B (Blue-White super giant) Star",
+        "F_WhiteSuperGiant" => "F (White super giant) Star",
+        "G_WhiteSuperGiant" => "G (White-Yellow super giant) Star",
+        "K_OrangeGiant" => "K (Yellow-Orange giant) Star",
+        "M_RedGiant" => "M (Red giant) Star",
+        "M_RedSuperGiant" => "M (Red super giant) Star",
+        white_dwarf if white_dwarf.starts_with('D') => return format!("White Dwarf ({}) Star", white_dwarf),
+        other => other,
+    };
+    name.to_string()
+}
+
+// Most systems a live feed has only seen half of: plenty of arrivals never
+// get a main star scan, so without a cap they'd pile up forever
+pub const DEFAULT_MAX_PENDING: usize = 100_000;
+
+pub struct JournalIngestor {
+    pending: HashMap<PendingKey, PendingSystem>,
+    // SystemAddresses of the Id64 pending entries by name, so events without
+    // one can still find them
+    pending_id64s: HashMap<String, Vec<u64>>,
+    ready: Vec<StarSystemRecord>,
+    // Past this many pending systems the least recently seen half is dropped
+    pub max_pending: usize,
+    pub events_seen: usize,
+    pub events_skipped: usize,
+    pub pending_dropped: usize,
+}
+
+impl Default for JournalIngestor {
+    fn default() -> Self {
+        Self {
+            pending: HashMap::new(),
+            pending_id64s: HashMap::new(),
+            ready: Vec::new(),
+            max_pending: DEFAULT_MAX_PENDING,
+            events_seen: 0,
+            events_skipped: 0,
+            pending_dropped: 0,
+        }
+    }
+}
+
+impl JournalIngestor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn process_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        self.events_seen += 1;
+        let event = match serde_json::from_str::<EddnEnvelope>(line) {
+            Ok(envelope) => envelope.message,
+            Err(_) => match serde_json::from_str::<JournalEvent>(line) {
+                Ok(event) => event,
+                Err(_) => {
+                    self.events_skipped += 1;
+                    return;
+                }
+            },
+        };
+        self.process_event(event);
+    }
+
+    fn process_event(&mut self, event: JournalEvent) {
+        let Some(name) = event.star_system else {
+            self.events_skipped += 1;
+            return;
+        };
+        let key = match event.event.as_str() {
+            "FSDJump" | "Location" | "CarrierJump" if event.star_pos.is_some() => {
+                let key = self.pending_entry(name, event.system_address);
+                self.pending.get_mut(&key).unwrap().coords = event.star_pos;
+                key
+            }
+            // The arrival star is the system's main star
+            "Scan" if event.star_type.is_some() && event.distance_from_arrival_ls == Some(0.0) => {
+                let key = self.pending_entry(name, event.system_address);
+                self.pending.get_mut(&key).unwrap().main_star_type = event.star_type.as_deref().map(journal_star_type_name);
+                key
+            }
+            _ => {
+                self.events_skipped += 1;
+                return;
+            }
+        };
+        let pending = self.pending.get_mut(&key).unwrap();
+        pending.id64 = event.system_address.or(pending.id64);
+        pending.last_seen = self.events_seen;
+        if pending.coords.is_some() && pending.main_star_type.is_some() {
+            let PendingSystem{name, id64, coords, main_star_type, ..} = self.pending.remove(&key).unwrap();
+            if let PendingKey::Id64(key_id64) = key {
+                self.forget_pending_id64(&name, key_id64);
+            }
+            let [x, y, z] = coords.unwrap();
+            self.ready.push(StarSystemRecord{
+                name,
+                Coord_X: x,
+                Coord_Y: y,
+                Coord_Z: z,
+                mainStarType: main_star_type.unwrap(),
+                d_from_sol: (x * x + y * y + z * z).sqrt(),
+                id64,
+            });
+        } else if self.pending.len() > self.max_pending {
+            self.drop_stale_pending();
+        }
+    }
+
+    // Finds or starts the pending entry an event belongs to. Events with a
+    // SystemAddress join its entry, or take over one started by name before
+    // the address was known. Events without one join the entry for their name,
+    // or the one system pending under that name with an address; a name shared
+    // by several is ambiguous, so it starts its own.
+    fn pending_entry(&mut self, name: String, system_address: Option<u64>) -> PendingKey {
+        let name_key = PendingKey::Name(name.clone());
+        let Some(id64) = system_address else {
+            return match self.pending_id64s.get(&name).map(|id64s| id64s.as_slice()) {
+                Some(&[id64]) if !self.pending.contains_key(&name_key) => PendingKey::Id64(id64),
+                _ => {
+                    self.pending.entry(name_key.clone()).or_default().name = name;
+                    name_key
+                }
+            };
+        };
+        let key = PendingKey::Id64(id64);
+        match self.pending.get_mut(&key) {
+            Some(pending) if pending.name == name => (),
+            Some(pending) => {
+                let old_name = std::mem::replace(&mut pending.name, name.clone());
+                self.forget_pending_id64(&old_name, id64);
+                self.pending_id64s.entry(name).or_default().push(id64);
+            }
+            None => {
+                let mut started = self.pending.remove(&name_key).unwrap_or_default();
+                started.name = name.clone();
+                self.pending.insert(key.clone(), started);
+                self.pending_id64s.entry(name).or_default().push(id64);
+            }
+        }
+        key
+    }
+
+    fn forget_pending_id64(&mut self, name: &str, id64: u64) {
+        if let Some(id64s) = self.pending_id64s.get_mut(name) {
+            id64s.retain(|&i| i != id64);
+            if id64s.is_empty() {
+                self.pending_id64s.remove(name);
+            }
+        }
+    }
+
+    // Keeps the more recently seen half of the pending systems. Dropping half
+    // at a time keeps the sweep rare.
+    fn drop_stale_pending(&mut self) {
+        let keep = self.max_pending.div_ceil(2).max(1);
+        let mut last_seen: Vec<usize> = self.pending.values().map(|p| p.last_seen).collect();
+        let cutoff_pos = last_seen.len() - keep;
+        let (_, &mut cutoff, _) = last_seen.select_nth_unstable(cutoff_pos);
+        let before = self.pending.len();
+        self.pending.retain(|_, p| p.last_seen >= cutoff);
+        self.pending_id64s.clear();
+        for (key, p) in &self.pending {
+            if let PendingKey::Id64(id64) = key {
+                self.pending_id64s.entry(p.name.clone()).or_default().push(*id64);
+            }
+        }
+        self.pending_dropped += before - self.pending.len();
+    }
+
+    pub fn ready_len(&self) -> usize {
+        self.ready.len()
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn take_ready(&mut self) -> Vec<StarSystemRecord> {
+        std::mem::take(&mut self.ready)
+    }
+}
+
+// Feeds every line of `reader` to the ingestor, writing a `.delta` file into
+// `output_dir` (for `apply_update`) whenever `batch_size` systems are ready and
+// once more at the end of the stream. `on_batch` is told each file's path and
+// system count as it's written. Returns the files written. If reading fails,
+// the systems ready by then are still written before the error is returned.
+pub fn ingest_journal<R: BufRead>(reader: R, ingestor: &mut JournalIngestor, output_dir: &str, batch_size: usize, mut on_batch: impl FnMut(&str, usize)) -> std::io::Result<Vec<String>> {
+    let mut written = Vec::new();
+    let mut write_batch = |ingestor: &mut JournalIngestor| -> std::io::Result<()> {
+        let (path, count) = write_ingest_batch(ingestor, output_dir)?;
+        on_batch(&path, count);
+        written.push(path);
+        Ok(())
+    };
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                if ingestor.ready_len() > 0 {
+                    write_batch(ingestor)?;
+                }
+                return Err(e);
+            }
+        };
+        ingestor.process_line(&line);
+        if ingestor.ready_len() >= batch_size {
+            write_batch(ingestor)?;
+        }
+    }
+    if ingestor.ready_len() > 0 {
+        write_batch(ingestor)?;
+    }
+    Ok(written)
+}
+
+// Returns the file written and how many systems are in it
+fn write_ingest_batch(ingestor: &mut JournalIngestor, output_dir: &str) -> std::io::Result<(String, usize)> {
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+    let mut path = format!("{}/ingest-{}.delta", output_dir, timestamp.as_millis());
+    let mut n = 1;
+    while std::path::Path::new(&path).exists() {
+        path = format!("{}/ingest-{}-{}.delta", output_dir, timestamp.as_millis(), n);
+        n += 1;
+    }
+    let delta = DatasetDelta{upserts: ingestor.take_ready(), removals: Vec::new()};
+    write_delta(&delta, &path)?;
+    Ok((path, delta.upserts.len()))
+}
@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use crate::{sort_star_system_records, StarSystem, StarSystemRecord};
+
+// Galaxy dumps from EDSM and Spansh are JSON arrays with one system object per
+// line, e.g.
+//   [
+//   {"id64":10477373803,"name":"Sol","coords":{"x":0,"y":0,"z":0},"mainStar":"G (White-Yellow) Star",...},
+//   ...
+//   ]
+// Plain JSON-lines files (no surrounding array) are read the same way.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct JsonSystem {
+    id64: Option<u64>,
+    name: String,
+    coords: JsonCoords,
+    #[serde(rename = "mainStar")]
+    main_star: Option<String>,
+    #[serde(rename = "primaryStar", skip_serializing_if = "Option::is_none")]
+    primary_star: Option<JsonPrimaryStar>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct JsonCoords {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct JsonPrimaryStar {
+    #[serde(rename = "type")]
+    star_type: Option<String>,
+}
+
+impl From<JsonSystem> for StarSystemRecord {
+    fn from(system: JsonSystem) -> StarSystemRecord {
+        let JsonSystem{id64, name, coords: JsonCoords{x, y, z}, main_star, primary_star} = system;
+        let main_star_type = main_star
+            .or_else(|| primary_star.and_then(|p| p.star_type))
+            .unwrap_or_default();
+        StarSystemRecord{
+            name,
+            Coord_X: x,
+            Coord_Y: y,
+            Coord_Z: z,
+            mainStarType: main_star_type,
+            d_from_sol: (x * x + y * y + z * z).sqrt(),
+            id64,
+        }
+    }
+}
+
+impl From<&StarSystemRecord> for JsonSystem {
+    fn from(record: &StarSystemRecord) -> JsonSystem {
+        JsonSystem{
+            id64: record.id64,
+            name: record.name.clone(),
+            coords: JsonCoords{x: record.Coord_X, y: record.Coord_Y, z: record.Coord_Z},
+            main_star: Some(record.mainStarType.clone()),
+            primary_star: None,
+        }
+    }
+}
+
+pub fn is_json_lines_file(filename: &str) -> bool {
+    [".json", ".jsonl", ".json.gz", ".jsonl.gz"].iter().any(|ext| filename.ends_with(ext))
+}
+
+pub struct JsonSystemRecords<R: BufRead> {
+    reader: R,
+    line: String,
+    line_number: usize,
+}
+
+impl JsonSystemRecords<BufReader<Box<dyn Read>>> {
+    // Gzipped dumps are detected by their magic bytes rather than the extension
+    pub fn open(filename: &str) -> std::io::Result<Self> {
+        let mut f = BufReader::new(File::open(filename)?);
+        let is_gzip = f.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let inner: Box<dyn Read> = if is_gzip {
+            Box::new(flate2::read::MultiGzDecoder::new(f))
+        } else {
+            Box::new(f)
+        };
+        Ok(Self::new(BufReader::with_capacity(1 << 20, inner)))
+    }
+}
+
+impl<R: BufRead> JsonSystemRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonSystemRecords<R> {
+    type Item = StarSystemRecord;
+
+    fn next(&mut self) -> Option<StarSystemRecord> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line).unwrap() == 0 {
+                return None;
+            }
+            self.line_number += 1;
+            let line = self.line.trim().trim_end_matches(',');
+            if line.is_empty() || line == "[" || line == "]" {
+                continue;
+            }
+            let system: JsonSystem = match serde_json::from_str(line) {
+                Ok(system) => system,
+                Err(e) => panic!("Bad system on line {}: {}", self.line_number, e),
+            };
+            return Some(system.into());
+        }
+    }
+}
+
+pub fn read_star_system_records_json(filename: &str) -> Vec<StarSystemRecord> {
+    let mut records: Vec<StarSystemRecord> = JsonSystemRecords::open(filename).unwrap().collect();
+    sort_star_system_records(&mut records);
+    records
+}
+
+pub fn read_star_systems_json(filename: &str, filter: fn(&StarSystem) -> bool) -> Vec<StarSystem> {
+    let mut retval: Vec<StarSystem> = JsonSystemRecords::open(filename).unwrap().map(|r| r.into()).filter(filter).collect();
+    retval.sort();
+    retval
+}
+
+fn write_json_records<W: Write>(records: &[StarSystemRecord], out: &mut W) -> std::io::Result<()> {
+    writeln!(out, "[")?;
+    for (i, record) in records.iter().enumerate() {
+        serde_json::to_writer(&mut *out, &JsonSystem::from(record))?;
+        writeln!(out, "{}", if i + 1 < records.len() { "," } else { "" })?;
+    }
+    writeln!(out, "]")
+}
+
+// Writes the dump layout above, gzipped when the filename ends in .gz
+pub fn write_star_system_records_json(records: &[StarSystemRecord], output_filepath: &str) -> std::io::Result<()> {
+    let mut out_f = BufWriter::new(File::create(output_filepath)?);
+    if output_filepath.ends_with(".gz") {
+        let mut gz = flate2::write::GzEncoder::new(out_f, flate2::Compression::default());
+        write_json_records(records, &mut gz)?;
+        out_f = gz.finish()?;
+    } else {
+        write_json_records(records, &mut out_f)?;
+    }
+    out_f.flush()
+}
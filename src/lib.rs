@@ -1,16 +1,53 @@
-#![feature(lazy_get)]
+use std::cell::Cell;
 use std::cmp::Ordering;
 use std::fs::File;
 use std::io::BufReader;
 use std::collections::{HashMap, BinaryHeap};
 use std::cmp::Reverse;
-use fast_fp::{FF32};
 use rayon::prelude::*;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::io::Seek;
 use memmap2::{Mmap};
 
-pub type Float = FF32;
+pub mod float;
+pub use float::{Float, FloatInner};
+pub mod columnar;
+pub use columnar::{ColumnarSystems, write_columnar_systems};
+pub mod sqlite_store;
+pub use sqlite_store::{import_star_systems_sqlite, read_star_systems_sqlite};
+pub mod json_import;
+pub use json_import::{JsonSystemRecords, is_json_lines_file, read_star_system_records_json, read_star_systems_json, write_star_system_records_json};
+pub mod update;
+pub use update::{DatasetDelta, DeltaSummary, apply_delta, read_delta, update_neutron_star_systems, write_delta};
+pub mod journal;
+pub use journal::{DEFAULT_MAX_PENDING, JournalIngestor, ingest_journal, journal_star_type_name};
+pub mod pipeline;
+pub mod neutron_grid;
+pub use neutron_grid::NeutronGrid;
+pub mod pruning;
+pub use pruning::{PruningPolicy, PruningReportRow, PruningRule, pruning_report};
+pub mod checkpoint;
+pub use checkpoint::build_neutron_file_checkpointed;
+pub mod graph_stats;
+pub use graph_stats::{GraphStats, RegionStats, graph_stats, write_outliers_csv};
+pub mod diff;
+pub use diff::{DatasetDiff, diff_datasets};
+pub mod reference;
+pub use reference::{is_valid_route, reference_route};
+pub mod validate;
+pub use validate::{FuelModel, HopProblem, Repair, RouteCheck, ShipParams, find_systems_by_name, validate_route};
+pub mod synthetic;
+pub mod budget;
+pub use budget::{CancelToken, SearchOptions, SearchOutcome, StopReason};
+use budget::{Budget, heap_bytes, map_bytes};
+pub mod progress;
+pub use progress::{LogProgress, Progress, ProgressStyle, SilentProgress, TerminalProgress};
+pub mod anytime;
+pub use anytime::{ANYTIME_WEIGHTS, AnytimeRoute, neutron_a_star_anytime};
+pub use synthetic::{DensityProfile, SyntheticGalaxy};
+pub use pipeline::{CookOptions, Manifest, ManifestFile, cook, open_data_dir, update_data_dir};
+
 pub type V3 = (Float, Float, Float);
 
 #[derive(Debug, Clone)]
@@ -20,14 +57,64 @@ pub struct StarSystem {
     pub coords: V3,
     pub distance_from_sol: Float,
     pub is_neutron: bool,
+    pub id64: u64,
 }
 
 #[derive(Debug, Clone, bincode::Encode, bincode::Decode)]
 pub struct NeutronStarSystem {
     pub idx: u32,
+    // id64 of the system at `idx`, used to find it again after the systems change
+    pub id64: u64,
     pub neighbors: Box<[u32]>,
 }
 
+// Layout of NeutronStarSystem before id64 was added
+#[derive(bincode::Decode)]
+struct LegacyNeutronStarSystem {
+    idx: u32,
+    neighbors: Box<[u32]>,
+}
+
+// Made-up address for a named system. Real system addresses never have the
+// top bit set, so these can't collide with them.
+pub fn name_id64(name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in name.as_bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash | (1 << 63)
+}
+
+// name_id64 for systems without an id64 in the dataset. Plenty of systems
+// share a name, so their position goes into the hash too.
+pub fn fallback_id64(name: &str, coords: [f32; 3]) -> u64 {
+    let mut hash = name_id64(name);
+    for c in coords {
+        for b in c.to_bits().to_le_bytes() {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash | (1 << 63)
+}
+
+// The first id64 listed more than once, if any
+pub fn duplicate_id64(mut id64s: Vec<u64>) -> Option<u64> {
+    id64s.par_sort_unstable();
+    id64s.windows(2).find(|w| w[0] == w[1]).map(|w| w[0])
+}
+
+// Systems sharing an id64 would clash in the sqlite index and get merged by
+// diffs and updates, so cooking refuses them up front
+pub fn check_unique_id64s(records: &[StarSystemRecord]) -> std::io::Result<()> {
+    let Some(id64) = duplicate_id64(records.par_iter().map(|r| r.system_id64()).collect()) else {
+        return Ok(());
+    };
+    let names: Vec<&str> = records.iter().filter(|r| r.system_id64() == id64).map(|r| r.name.as_str()).collect();
+    Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} systems share id64 {} ({}), drop the duplicates or give them their own id64", names.len(), id64, names.join(", "))))
+}
+
 fn square(a: Float) -> Float {
     a * a
 }
@@ -40,9 +127,49 @@ pub fn distance(a: &StarSystem, b: &StarSystem) -> Float {
     distance_v(&a.coords, &b.coords)
 }
 
+pub fn system_distance<S: Systems + ?Sized>(systems: &S, a: u32, b: u32) -> Float {
+    distance_v(&systems.coords(a), &systems.coords(b))
+}
+
+// FNV-1a over every system's id64 and position, in routing order. Identifies
+// the exact system list a neutron file's indexes point into.
+pub fn dataset_hash<I: Iterator<Item = (u64, V3)>>(systems: I) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (id64, (x, y, z)) in systems {
+        for word in [id64, f32::from(x).to_bits() as u64, f32::from(y).to_bits() as u64, f32::from(z).to_bits() as u64] {
+            for b in word.to_le_bytes() {
+                hash ^= b as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+    hash
+}
+
+// Read access to a galaxy sorted by distance from Sol, as needed by the searches.
+pub trait Systems: Sync {
+    fn len(&self) -> u32;
+    fn coords(&self, idx: u32) -> V3;
+    fn distance_from_sol(&self, idx: u32) -> Float;
+    fn is_neutron(&self, idx: u32) -> bool;
+    fn name(&self, idx: u32) -> &str;
+    fn main_star_type(&self, idx: u32) -> &str;
+    fn id64(&self, idx: u32) -> u64;
+
+    fn find_by_id64(&self, id64: u64) -> Option<u32> {
+        (0..self.len()).into_par_iter().find_any(|&idx| self.id64(idx) == id64)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Ties in distance are broken by id64, so the systems end up in the same
+// order whatever order the dump listed them in
 impl Ord for StarSystem {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.distance_from_sol.partial_cmp(&other.distance_from_sol).unwrap()
+        self.distance_from_sol.cmp(&other.distance_from_sol).then(self.id64.cmp(&other.id64))
     }
 }
 
@@ -60,7 +187,9 @@ impl PartialEq for StarSystem {
 
 impl Eq for StarSystem {}
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, bincode::Encode, bincode::Decode)]
+// Field names match the CSV dump's columns
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, bincode::Encode, bincode::Decode)]
+#[allow(non_snake_case)]
 pub struct StarSystemRecord {
     pub name: String,
     pub Coord_X: f32,
@@ -68,10 +197,59 @@ pub struct StarSystemRecord {
     pub Coord_Z: f32,
     pub mainStarType: String,
     pub d_from_sol: f32,
+    #[serde(default)]
+    pub id64: Option<u64>,
+}
+
+impl StarSystemRecord {
+    // The dataset's id64, or a stand-in for records without one
+    pub fn system_id64(&self) -> u64 {
+        self.id64.unwrap_or_else(|| fallback_id64(&self.name, [self.Coord_X, self.Coord_Y, self.Coord_Z]))
+    }
+}
+
+// Layout of StarSystemRecord before id64 was added, still found in older bincode dumps
+#[derive(bincode::Decode)]
+#[allow(non_snake_case)]
+struct LegacyStarSystemRecord {
+    name: String,
+    Coord_X: f32,
+    Coord_Y: f32,
+    Coord_Z: f32,
+    mainStarType: String,
+    d_from_sol: f32,
+}
+
+impl From<LegacyStarSystemRecord> for StarSystemRecord {
+    fn from(record: LegacyStarSystemRecord) -> StarSystemRecord {
+        let LegacyStarSystemRecord{
+            name,
+            Coord_X,
+            Coord_Y,
+            Coord_Z,
+            mainStarType,
+            d_from_sol,
+        } = record;
+        StarSystemRecord{
+            name,
+            Coord_X,
+            Coord_Y,
+            Coord_Z,
+            mainStarType,
+            d_from_sol,
+            id64: None,
+        }
+    }
+}
+
+// The same order as StarSystem's: distance from Sol, then id64
+pub fn sort_star_system_records(records: &mut [StarSystemRecord]) {
+    records.sort_by_cached_key(|r| (Float::from(r.d_from_sol), r.system_id64()));
 }
 
 impl From<StarSystemRecord> for StarSystem {
     fn from(record: StarSystemRecord) -> StarSystem {
+        let id64 = record.system_id64();
         let StarSystemRecord{
             name,
             Coord_X,
@@ -79,8 +257,10 @@ impl From<StarSystemRecord> for StarSystem {
             Coord_Z,
             mainStarType,
             d_from_sol,
+            ..
         } = record;
         StarSystem {
+            id64,
             name,
             coords: (Coord_X.into(), Coord_Y.into(), Coord_Z.into()),
             is_neutron: mainStarType == "Neutron Star",
@@ -97,6 +277,7 @@ impl From<StarSystem> for StarSystemRecord {
             coords: (x, y, z),
             main_star_type,
             distance_from_sol,
+            id64,
             ..
         } = record;
         StarSystemRecord{
@@ -106,17 +287,32 @@ impl From<StarSystem> for StarSystemRecord {
             Coord_Z: z.into(),
             mainStarType: main_star_type,
             d_from_sol: distance_from_sol.into(),
+            id64: Some(id64),
         }
     }
 }
 
 
+pub fn read_star_system_records_csv(filename: &str) -> Vec<StarSystemRecord> {
+    let mut reader = csv::Reader::from_path(filename).unwrap();
+    let mut records: Vec<StarSystemRecord> = reader.deserialize().map(|r| r.unwrap()).collect();
+    sort_star_system_records(&mut records);
+    records
+}
+
+pub fn write_star_system_records_csv(records: &[StarSystemRecord], output_filepath: &str) -> std::io::Result<()> {
+    let mut writer = csv::Writer::from_path(output_filepath)?;
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()
+}
+
 pub fn read_star_systems_csv(filename: &str, filter: fn(&StarSystem) -> bool) -> Vec<StarSystem> {
     let f = File::open(filename).unwrap();
     let r = BufReader::new(f);
     let mut reader = csv::Reader::from_reader(r);
-    let mut retval = Vec::new();
-    retval.reserve(160_000_000);
+    let mut retval = Vec::with_capacity(160_000_000);
     for result in reader.deserialize::<StarSystemRecord>() {
         let system: StarSystem = result.unwrap().into();
         if filter(&system) {
@@ -124,52 +320,118 @@ pub fn read_star_systems_csv(filename: &str, filter: fn(&StarSystem) -> bool) ->
         }
     }
     retval.sort();
-    return retval;
+    retval
 }
 
-pub fn read_star_systems_bincode(filename: &str, filter: fn(&StarSystem) -> bool) -> Vec<StarSystem> {
+// Prefix of gzipped bincode system dumps written since id64 was added to
+// StarSystemRecord. Dumps without it are decoded with the legacy layout.
+const SYSTEMS_BINCODE_MAGIC: &[u8; 8] = b"NSSYSv02";
+
+pub fn write_star_systems_bincode(records: &[StarSystemRecord], output_filepath: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let out_f = File::create(output_filepath)?;
+    let mut compressed_out = flate2::write::GzEncoder::new(out_f, flate2::Compression::best());
+    compressed_out.write_all(SYSTEMS_BINCODE_MAGIC)?;
+    bincode::encode_into_std_write(records, &mut compressed_out, bincode::config::standard()).map_err(std::io::Error::other)?;
+    compressed_out.finish()?;
+    Ok(())
+}
+
+pub fn read_star_system_records_bincode(filename: &str) -> Vec<StarSystemRecord> {
+    use std::io::Read;
     let f = File::open(filename).unwrap();
     let mut gz = flate2::read::GzDecoder::new(f);
-    let records: Vec<StarSystemRecord> = bincode::decode_from_std_read(&mut gz, bincode::config::standard()).unwrap();
+    let mut magic = Vec::new();
+    (&mut gz).take(SYSTEMS_BINCODE_MAGIC.len() as u64).read_to_end(&mut magic).unwrap();
+    if magic == SYSTEMS_BINCODE_MAGIC {
+        bincode::decode_from_std_read(&mut gz, bincode::config::standard()).unwrap()
+    } else {
+        let mut legacy = std::io::Cursor::new(magic).chain(gz);
+        let records: Vec<LegacyStarSystemRecord> = bincode::decode_from_std_read(&mut legacy, bincode::config::standard()).unwrap();
+        records.into_iter().map(|r| r.into()).collect()
+    }
+}
+
+pub fn read_star_systems_bincode(filename: &str, filter: fn(&StarSystem) -> bool) -> Vec<StarSystem> {
+    let records = read_star_system_records_bincode(filename);
     records.into_iter().map(|r| r.into()).filter(filter).collect()
 }
 
+pub fn read_star_systems(filename: &str, filter: fn(&StarSystem) -> bool) -> Vec<StarSystem> {
+    if filename.ends_with(".csv") {
+        read_star_systems_csv(filename, filter)
+    } else if is_json_lines_file(filename) {
+        read_star_systems_json(filename, filter)
+    } else if filename.ends_with(".sqlite") || filename.ends_with(".db") {
+        read_star_systems_sqlite(filename, filter).unwrap()
+    } else {
+        read_star_systems_bincode(filename, filter)
+    }
+}
+
+// Prefix of neutron bincode files written since id64 was added to NeutronStarSystem
+const NEUTRONS_BINCODE_MAGIC: &[u8; 8] = b"NSNBRv02";
+
+pub fn write_neutron_stars_bincode(neutrons: &[NeutronStarSystem], output_filepath: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let out_f = File::create(output_filepath)?;
+    let mut buf_out = std::io::BufWriter::new(out_f);
+    buf_out.write_all(NEUTRONS_BINCODE_MAGIC)?;
+    bincode::encode_into_std_write(neutrons, &mut buf_out, bincode::config::standard()).map_err(std::io::Error::other)?;
+    buf_out.flush()
+}
+
+// Reads plain or gzipped neutron files. Files from before id64 was added come
+// back with an id64 of 0; re-cook them with `cook` to fill it in.
 pub fn read_neutron_stars_bincode(filename: &str) -> Box<[NeutronStarSystem]> {
+    use std::io::{BufRead, Read};
     let f = File::open(filename).unwrap();
-    //let mut gz = flate2::read::GzDecoder::new(f);
     let mut buf = BufReader::new(f);
-    let records: Vec<NeutronStarSystem> = bincode::decode_from_std_read(&mut buf, bincode::config::standard()).unwrap();
-    records.into_iter().collect()
+    let mut reader: Box<dyn Read> = if buf.fill_buf().unwrap().starts_with(&[0x1f, 0x8b]) {
+        Box::new(BufReader::new(flate2::read::GzDecoder::new(buf)))
+    } else {
+        Box::new(buf)
+    };
+    let mut magic = Vec::new();
+    (&mut reader).take(NEUTRONS_BINCODE_MAGIC.len() as u64).read_to_end(&mut magic).unwrap();
+    if magic == NEUTRONS_BINCODE_MAGIC {
+        let records: Vec<NeutronStarSystem> = bincode::decode_from_std_read(&mut reader, bincode::config::standard()).unwrap();
+        records.into_boxed_slice()
+    } else {
+        let mut legacy = std::io::Cursor::new(magic).chain(reader);
+        let records: Vec<LegacyNeutronStarSystem> = bincode::decode_from_std_read(&mut legacy, bincode::config::standard()).unwrap();
+        records.into_iter().map(|n| NeutronStarSystem{idx: n.idx, id64: 0, neighbors: n.neighbors}).collect()
+    }
 }
 
-fn neighbors(systems: &VecMap<StarSystem>, system_idx: u32, mut jump_distance: Float) -> Vec<u32> {
-    let system = systems.get(system_idx);
-    if system.is_neutron {
+// Every system one jump from `system_idx`: within `jump_distance`, or four
+// times that from a neutron star
+pub fn neighbors<S: Systems>(systems: &S, system_idx: u32, mut jump_distance: Float) -> Vec<u32> {
+    if systems.is_neutron(system_idx) {
         jump_distance *= Float::from(4.0);
     }
-    let mut retval = Vec::new();
-    retval.reserve(256);
+    let coords = systems.coords(system_idx);
+    let distance_from_sol = systems.distance_from_sol(system_idx);
+    let mut retval = Vec::with_capacity(256);
     for neighbor_idx in (system_idx+1)..systems.len() {
-        let neighbor = systems.get(neighbor_idx);
-        if neighbor.distance_from_sol > system.distance_from_sol + jump_distance {
+        if systems.distance_from_sol(neighbor_idx) > distance_from_sol + jump_distance {
             break;
         }
-        if distance(&system, &neighbor) <= jump_distance {
+        if distance_v(&coords, &systems.coords(neighbor_idx)) <= jump_distance {
             retval.push(neighbor_idx);
         }
     }
     if system_idx > 0 {
-        for neighbor_idx in (0..(system_idx-1)).rev() {
-            let neighbor = systems.get(neighbor_idx);
-            if neighbor.distance_from_sol < system.distance_from_sol - jump_distance {
+        for neighbor_idx in (0..system_idx).rev() {
+            if systems.distance_from_sol(neighbor_idx) < distance_from_sol - jump_distance {
                 break;
             }
-            if distance(&system, &neighbor) <= jump_distance {
+            if distance_v(&coords, &systems.coords(neighbor_idx)) <= jump_distance {
                 retval.push(neighbor_idx);
             }
         }
     }
-    return retval;
+    retval
 }
 
 #[derive(Debug,Copy,Clone)]
@@ -180,7 +442,7 @@ struct HScore {
 
 impl Ord for HScore {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.jumps.cmp(&other.jumps).then(self.distance.partial_cmp(&other.distance).unwrap())
+        self.jumps.cmp(&other.jumps).then(self.distance.cmp(&other.distance))
     }
 }
 
@@ -198,19 +460,117 @@ impl PartialEq for HScore {
 
 impl Eq for HScore {}
 
+impl HScore {
+    // An estimate counted `weight` times, for weighted A*
+    fn weighted(self, weight: f32) -> HScore {
+        HScore{jumps: (self.jumps as f32 * weight).round() as i64, distance: self.distance * Float::from(weight)}
+    }
+}
+
+
+// Work done by searches on the current thread since the last reset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchCounters {
+    // Systems expanded by a_star, including the searches inside neutron_a_star
+    pub expansions: u64,
+    // Neutron stars expanded by the neutron graph search
+    pub neutron_expansions: u64,
+}
+
+thread_local! {
+    static SEARCH_COUNTERS: Cell<SearchCounters> = Cell::new(SearchCounters::default());
+}
+
+pub fn search_counters() -> SearchCounters {
+    SEARCH_COUNTERS.with(|counters| counters.get())
+}
+
+pub fn reset_search_counters() {
+    SEARCH_COUNTERS.with(|counters| counters.set(SearchCounters::default()));
+}
+
+fn count_expansion(neutron: bool) {
+    SEARCH_COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        if neutron {
+            c.neutron_expansions += 1;
+        } else {
+            c.expansions += 1;
+        }
+        counters.set(c);
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    // Quick estimates that usually, but not always, find the fewest jumps
+    #[default]
+    Fast,
+    // Admissible, consistent heuristics: routes always have the fewest jumps
+    Exact,
+}
+
+// Keeps the exact heuristic a lower bound despite f32 rounding in d / range
+const EXACT_HEURISTIC_EPSILON: f32 = 1e-4;
+
+// Fewest jumps that could cover `distance` when every jump is at most `range`
+fn min_jumps(distance: Float, range: Float) -> i64 {
+    let jumps = f32::from(distance / range) - EXACT_HEURISTIC_EPSILON;
+    jumps.ceil().max(0.0) as i64
+}
+
+// Lower bound on the jumps from `system_idx` to the goal `distance` away. Any
+// jump covers at most 4 * jump_distance (if it leaves a neutron star), and the
+// first covers at most jump_distance unless `system_idx` is a neutron star.
+// Consistent: a jump of length l changes the distance to the goal by at most
+// l, which is within the range this bound assumes for the jump, so the bound
+// drops by at most 1 per jump.
+pub(crate) fn exact_h_jumps<S: Systems>(systems: &S, system_idx: u32, distance: Float, jump_distance: Float) -> i64 {
+    let boosted = jump_distance * Float::from(4.0);
+    if distance <= Float::from(0.0) {
+        0
+    } else if systems.is_neutron(system_idx) {
+        min_jumps(distance, boosted).max(1)
+    } else if distance <= jump_distance {
+        1
+    } else {
+        1 + min_jumps(distance - jump_distance, boosted)
+    }
+}
+
+pub fn a_star<S: Systems>(systems: &S, start_idx: u32, goal_idx: u32, jump_distance: Float, max_jumps: Option<i64>) -> Option<Vec<u32>> {
+    a_star_with_mode(systems, start_idx, goal_idx, jump_distance, max_jumps, SearchMode::Fast)
+}
+
+// Guarantee in SearchMode::Exact: the route returned has the fewest jumps of
+// any route from start to goal where each jump is at most `jump_distance`, or
+// at most 4 * `jump_distance` when it leaves a neutron star. None means no
+// such route exists (with at most `max_jumps` jumps, if given).
+pub fn a_star_with_mode<S: Systems>(systems: &S, start_idx: u32, goal_idx: u32, jump_distance: Float, max_jumps: Option<i64>, mode: SearchMode) -> Option<Vec<u32>> {
+    a_star_with_options(systems, start_idx, goal_idx, jump_distance, max_jumps, &SearchOptions { mode, ..SearchOptions::default() }).route()
+}
+
+// a_star_with_mode that gives up when `options` runs out. It has no complete
+// route until it reaches the goal, so a stopped search has no best route.
+pub fn a_star_with_options<S: Systems>(systems: &S, start_idx: u32, goal_idx: u32, jump_distance: Float, max_jumps: Option<i64>, options: &SearchOptions) -> SearchOutcome {
+    let mut budget = Budget::new(options);
+    budget.progress().begin(&format!("Searching {} to {} distance {}", systems.name(start_idx), systems.name(goal_idx), system_distance(systems, start_idx, goal_idx)), None);
+    let outcome = a_star_budgeted(systems, start_idx, goal_idx, jump_distance, max_jumps, options.mode, &mut budget);
+    budget.end();
+    outcome
+}
 
-pub fn a_star(systems: &VecMap<StarSystem>, start_idx: u32, goal_idx: u32, jump_distance: Float, max_jumps: Option<i64>) -> Option<Vec<u32>> {
-    let start = systems.get(start_idx);
-    let goal = systems.get(goal_idx);
-    println!("Searching {} to {} distance {}", start.name, goal.name, distance(start, goal));
+fn a_star_budgeted<S: Systems>(systems: &S, start_idx: u32, goal_idx: u32, jump_distance: Float, max_jumps: Option<i64>, mode: SearchMode, budget: &mut Budget) -> SearchOutcome {
     let h_fn = |system_idx: u32| -> HScore {
-        let system = systems.get(system_idx);
         if system_idx == goal_idx {
             let jumps = 0;
             let distance: Float = 0.0.into();
             HScore{jumps, distance}
-        } else if system.is_neutron {
-            let distance = distance(&system, &goal);
+        } else if mode == SearchMode::Exact {
+            let distance = system_distance(systems, system_idx, goal_idx);
+            HScore{jumps: exact_h_jumps(systems, system_idx, distance, jump_distance), distance}
+        } else if systems.is_neutron(system_idx) {
+            let distance = system_distance(systems, system_idx, goal_idx);
             let after_neutron_distance: Float = distance - (jump_distance * Float::from(4.0));
             if after_neutron_distance <= 0.0.into() {
                 return HScore{jumps: 1, distance};
@@ -218,7 +578,7 @@ pub fn a_star(systems: &VecMap<StarSystem>, start_idx: u32, goal_idx: u32, jump_
             let jumps = f32::from((after_neutron_distance / jump_distance).ceil()) as i64 + 1;
             HScore{jumps, distance}
         } else {
-            let distance = distance(&system, &goal);
+            let distance = system_distance(systems, system_idx, goal_idx);
             let jumps = f32::from((distance / jump_distance).ceil()) as i64;
             HScore{jumps, distance}
         }
@@ -231,41 +591,45 @@ pub fn a_star(systems: &VecMap<StarSystem>, start_idx: u32, goal_idx: u32, jump_
     let start_h_score = HScore{jumps:0, distance:0.0.into()};
     g_score.insert(start_idx, start_h_score);
     h_score.insert(start_idx, start_h_score);
-    to_visit.push(Reverse((start_h_score, start_idx)));
+    // Equal scores are popped in id64 order, so which of several equally good
+    // routes comes back doesn't depend on how the systems are laid out
+    to_visit.push(Reverse((start_h_score, systems.id64(start_idx), start_idx)));
 
     let mut max_g = 0;
-    while let Some(Reverse((current_h_score, current_idx))) = to_visit.pop() {
+    while let Some(Reverse((current_h_score, _, current_idx))) = to_visit.pop() {
         if current_idx == goal_idx {
             break;
         }
         if current_h_score > h_score[&current_idx] {
             continue;
         }
+        let state_bytes = map_bytes(&parent) + map_bytes(&g_score) + map_bytes(&h_score) + heap_bytes(&to_visit);
+        if let Err(reason) = budget.spend(state_bytes) {
+            return SearchOutcome::Stopped { reason, best: None };
+        }
+        count_expansion(false);
         let cur_g = g_score[&current_idx];
         if cur_g.jumps > max_g {
             max_g = cur_g.jumps;
             //println!("Looking at range {}", max_g);
         }
-        let current = systems.get(current_idx);
-        //println!("[A] Processing {} {:?}\n {:?} {:?}", current.name, current.coords, cur_g, current_h_score);
+        //println!("[A] Processing {} {:?}\n {:?} {:?}", systems.name(current_idx), systems.coords(current_idx), cur_g, current_h_score);
         for neighbor_idx in neighbors(systems, current_idx, jump_distance) {
             let new_g = HScore{
                 jumps: cur_g.jumps+1,
-                distance: cur_g.distance + distance(&systems.get(current_idx), &systems.get(neighbor_idx)),
+                distance: cur_g.distance + system_distance(systems, current_idx, neighbor_idx),
             };
             let mut new_h = h_fn(neighbor_idx);
             new_h.jumps += new_g.jumps;
             new_h.distance += new_g.distance;
             if !h_score.contains_key(&neighbor_idx) || new_h < h_score[&neighbor_idx] {
-                if let Some(max_jumps) = max_jumps {
-                    if new_h.jumps > max_jumps {
-                        continue;
-                    }
+                if max_jumps.is_some_and(|max_jumps| new_h.jumps > max_jumps) {
+                    continue;
                 }
                 *h_score.entry(neighbor_idx).or_insert(new_h) = new_h;
                 *g_score.entry(neighbor_idx).or_insert(new_g) = new_g;
                 *parent.entry(neighbor_idx).or_insert(0) = current_idx;
-                to_visit.push(Reverse((new_h, neighbor_idx)));
+                to_visit.push(Reverse((new_h, systems.id64(neighbor_idx), neighbor_idx)));
             }
         }
     }
@@ -283,19 +647,54 @@ pub fn a_star(systems: &VecMap<StarSystem>, start_idx: u32, goal_idx: u32, jump_
     }
 
     if good {
-        return Some(path.into_iter().rev().collect());
+        return SearchOutcome::Found(path.into_iter().rev().collect());
     }
 
-    None
+    SearchOutcome::NoRoute
 }
 
-pub async fn neutron_a_star(systems: &VecMap<StarSystem>, neutron_systems: &Arc<IndexedFileMap<NeutronStarSystem>>, start_idx: u32, goal_idx: u32, jump_distance: Float) -> Option<Vec<u32>> {
-    let start = systems.get(start_idx);
-    let goal = systems.get(goal_idx);
+pub fn neutron_a_star<S: Systems>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float) -> Option<Vec<u32>> {
+    neutron_a_star_with_mode(systems, neutron_systems, start_idx, goal_idx, jump_distance, SearchMode::Fast)
+}
 
-    let total_distance = distance(&start, &goal);
+// In SearchMode::Exact the neutron graph route only serves as an upper bound
+// for an exact search over every system, so the result carries the guarantee
+// documented on `a_star_with_mode`.
+pub fn neutron_a_star_with_mode<S: Systems>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float, mode: SearchMode) -> Option<Vec<u32>> {
+    neutron_a_star_with_options(systems, neutron_systems, start_idx, goal_idx, jump_distance, &SearchOptions { mode, ..SearchOptions::default() }).route()
+}
 
-    println!("Neutron star count: {}", neutron_systems.len());
+// neutron_a_star_with_mode that gives up when `options` runs out, with the
+// best route through the neutron stars seen so far. In SearchMode::Exact a
+// stopped exact search falls back to the neutron graph route.
+pub fn neutron_a_star_with_options<S: Systems>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float, options: &SearchOptions) -> SearchOutcome {
+    let mut budget = Budget::new(options);
+    budget.progress().begin(&format!("Neutron route {} to {}", systems.name(start_idx), systems.name(goal_idx)), None);
+    let outcome = neutron_a_star_budgeted(systems, neutron_systems, start_idx, goal_idx, jump_distance, options.mode, &mut budget);
+    budget.end();
+    outcome
+}
+
+fn neutron_a_star_budgeted<S: Systems>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float, mode: SearchMode, budget: &mut Budget) -> SearchOutcome {
+    let graph_outcome = neutron_graph_a_star(systems, neutron_systems, start_idx, goal_idx, jump_distance, budget);
+    if mode == SearchMode::Fast {
+        return graph_outcome;
+    }
+    let graph_route = match graph_outcome {
+        SearchOutcome::Found(route) => Some(route),
+        SearchOutcome::NoRoute => None,
+        stopped => return stopped,
+    };
+    let bound = graph_route.as_ref().map(|path| path.len() as i64);
+    match a_star_budgeted(systems, start_idx, goal_idx, jump_distance, bound, SearchMode::Exact, budget) {
+        SearchOutcome::NoRoute => graph_route.map_or(SearchOutcome::NoRoute, SearchOutcome::Found),
+        SearchOutcome::Stopped { reason, .. } => SearchOutcome::Stopped { reason, best: graph_route },
+        found => found,
+    }
+}
+
+fn neutron_graph_a_star<S: Systems>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float, budget: &mut Budget) -> SearchOutcome {
+    budget.progress().note(&format!("Neutron star count: {}", neutron_systems.len()));
 
     // let n_to_n_distance: HashMap<(usize, usize), Float> = neutron_systems.iter().enumerate().flat_map(|(i, &start)| {
         // (&neutron_systems[i+1..]).iter().map(move |&end| {
@@ -303,11 +702,23 @@ pub async fn neutron_a_star(systems: &VecMap<StarSystem>, neutron_systems: &Arc<
         // })
     // }).collect();
 
+    let no_neutron_path = match a_star_budgeted(systems, start_idx, goal_idx, jump_distance, None, SearchMode::Fast, budget) {
+        SearchOutcome::Found(path) => path,
+        outcome => return outcome,
+    };
+    budget.progress().note(&format!("No neutron length: {}", no_neutron_path.len()));
+    neutron_graph_run(systems, neutron_systems, start_idx, goal_idx, jump_distance, Some(no_neutron_path), 1.0, budget)
+}
+
+// One weighted A* pass over the neutron graph: the estimated part of every
+// score (jumps not yet searched for) counts `weight` times, which finds a
+// route sooner the higher it is. Routes no shorter than `incumbent` are
+// pruned; without one the goal starts out reachable straight from the start.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn neutron_graph_run<S: Systems>(systems: &S, neutron_systems: &NeutronFileMap, start_idx: u32, goal_idx: u32, jump_distance: Float, incumbent: Option<Vec<u32>>, weight: f32, budget: &mut Budget) -> SearchOutcome {
     let h_fn = |from_idx: u32, system_idx: u32| -> HScore {
-        let system = systems.get(system_idx);
-        let from = systems.get(from_idx);
-        let from_distance = distance(&from, &system);
-        let from_jumps = if from.is_neutron {
+        let from_distance = system_distance(systems, from_idx, system_idx);
+        let from_jumps = if systems.is_neutron(from_idx) {
             let after_first_jump_distance: Float = from_distance - jump_distance * Float::from(4.0);
             if after_first_jump_distance < 0.0.into() {
                 1
@@ -317,14 +728,33 @@ pub async fn neutron_a_star(systems: &VecMap<StarSystem>, neutron_systems: &Arc<
         } else {
             f32::from((from_distance / jump_distance).ceil()) as i64
         };
-        let goal_distance = distance(&system, &goal) / 4.0;
+        let goal_distance = system_distance(systems, system_idx, goal_idx) / 4.0;
         let goal_jumps = f32::from((goal_distance / jump_distance).ceil()) as i64;
         let jumps = from_jumps + goal_jumps;
         let distance = from_distance + (goal_distance * Float::from(4.0));
         HScore{jumps, distance}
     };
+    // Jumps straight to the goal, the first from a neutron star if `from_idx` is one
+    let to_goal_fn = |from_idx: u32| -> HScore {
+        let to_goal_distance = system_distance(systems, from_idx, goal_idx);
+        let to_goal_jumps = if !systems.is_neutron(from_idx) {
+            f32::from((to_goal_distance / jump_distance).ceil()) as i64
+        } else {
+            let after_first_jump_to_goal_distance = to_goal_distance - (jump_distance * 4.0);
+            if after_first_jump_to_goal_distance <= 0.0.into() {
+                1
+            } else {
+                1 + f32::from((after_first_jump_to_goal_distance / jump_distance).ceil()) as i64
+            }
+        };
+        HScore{jumps: to_goal_jumps, distance: to_goal_distance}
+    };
+    let key = |g: HScore, h: HScore| -> HScore {
+        let h = h.weighted(weight);
+        HScore{jumps: g.jumps + h.jumps, distance: g.distance + h.distance}
+    };
 
-    let RESERVE_SIZE: usize = 500_000;
+    const RESERVE_SIZE: usize = 500_000;
 
 
     let mut g_score = HashMap::new();
@@ -339,213 +769,192 @@ pub async fn neutron_a_star(systems: &VecMap<StarSystem>, neutron_systems: &Arc<
     let start_h_score = HScore{jumps: 0, distance: 0.0.into()};
     g_score.insert(start_idx, start_h_score);
     h_score.insert(start_idx, start_h_score);
-    
-    let no_neutron_path = a_star(systems, start_idx, goal_idx, jump_distance, None)?;
-    {
-        for &idx in &no_neutron_path {
-            let system = systems.get(idx);
-            println!("{}", system.name);
+
+    // The route from each system's parent to it, where a search found one
+    let mut subpaths: HashMap<u32, (u32, Vec<u32>)> = HashMap::new();
+    let incumbent_len = incumbent.as_ref().map(|path| path.len() as i64);
+    let incumbent_h_score = HScore{jumps: incumbent_len.unwrap_or(i64::MAX), distance: 0.0.into()};
+    let goal_h_score = match incumbent {
+        Some(path) => {
+            subpaths.insert(goal_idx, (start_idx, path));
+            incumbent_h_score
         }
-    }
-    let no_neutron_len = no_neutron_path.len() as i64;
-    println!("No neutron length: {}", no_neutron_len);
-    let no_neutron_h_score = HScore{jumps: no_neutron_len, distance: 0.0.into()};
-    h_score.insert(goal_idx, no_neutron_h_score);
+        None => key(start_h_score, to_goal_fn(start_idx)),
+    };
+    h_score.insert(goal_idx, goal_h_score);
     parent.insert(goal_idx, start_idx);
 
-    to_visit.push(Reverse((no_neutron_h_score, goal_idx, 0)));
-    let mut n_idx_idx: u32 = 0;
-    let mut idx_lookup = vec![032; neutron_systems.len() as usize];
-    let p_results: Vec<(u32, u32, HScore)> = (0..systems.len()).filter(|i| systems.get(*i).is_neutron).enumerate().par_bridge().map(|(n_idx_idx, n_idx)| {
-        let system = systems.get(n_idx);
-        let h = h_fn(start_idx, n_idx);
-        (n_idx_idx as u32, n_idx, h)
-    }).collect();
+    let is_one_jump = |from_idx: u32, to_idx: u32| -> bool {
+        let distance = system_distance(systems, from_idx, to_idx);
+        (systems.is_neutron(from_idx) && distance <= jump_distance * 4.0) || distance <= jump_distance
+    };
+    // Start to goal through the parents found so far. Legs that are neither
+    // stored subpaths nor a single jump are searched for within `budget`, or
+    // without one, leave no route.
+    let build_route = |parent: &HashMap<u32, u32>, subpaths: &HashMap<u32, (u32, Vec<u32>)>, mut budget: Option<&mut Budget>| -> Result<Option<Vec<u32>>, StopReason> {
+        let mut path_idx = goal_idx;
+        let mut path: Vec<u32> = Vec::new();
+        while let Some(&parent_idx) = parent.get(&path_idx) {
+            let subpath = match (subpaths.get(&path_idx), budget.as_deref_mut()) {
+                (Some((from_idx, subpath)), _) if *from_idx == parent_idx => subpath.clone(),
+                _ if is_one_jump(parent_idx, path_idx) => vec![path_idx],
+                (_, Some(budget)) => match a_star_budgeted(systems, parent_idx, path_idx, jump_distance, None, SearchMode::Fast, budget) {
+                    SearchOutcome::Found(subpath) => subpath,
+                    SearchOutcome::NoRoute => return Ok(None),
+                    SearchOutcome::Stopped { reason, .. } => return Err(reason),
+                },
+                (_, None) => return Ok(None),
+            };
+            path.extend(subpath.into_iter().rev());
+            if parent_idx == start_idx {
+                return Ok(Some(path.into_iter().rev().collect()));
+            }
+            path_idx = parent_idx;
+        }
+        Ok(None)
+    };
+    // Once the budget has run out, the best route is whatever can be pieced
+    // together without searching
+    let stopped = |reason: StopReason, parent: &HashMap<u32, u32>, subpaths: &HashMap<u32, (u32, Vec<u32>)>| -> SearchOutcome {
+        let best = build_route(parent, subpaths, None).unwrap_or(None);
+        SearchOutcome::Stopped { reason, best }
+    };
 
-    let mut precache: Vec<_> = std::iter::repeat(neutron_systems).take(neutron_systems.len() as usize).enumerate().map(|(i, neutron_systems)| {
-        let i = i as u32;
-        std::cell::LazyCell::new(move || {
-            let neutron_systems: Arc<_> = neutron_systems.clone();
-            tokio::task::spawn(async move {
-                neutron_systems.get(i)
-            })
-        })
+    // Ties are broken by id64, as in a_star
+    to_visit.push(Reverse((goal_h_score, systems.id64(goal_idx), goal_idx, 0)));
+    let p_results: Vec<(u32, u32, HScore)> = (0..neutron_systems.len()).into_par_iter().map(|n_idx_idx| {
+        let n_idx = neutron_systems.idx(n_idx_idx);
+        let h = h_fn(start_idx, n_idx);
+        (n_idx_idx, n_idx, h)
     }).collect();
-        
 
     for (n_idx_idx, n_idx, h) in p_results {
-        idx_lookup[n_idx_idx as usize] = n_idx;
-        if h < no_neutron_h_score {
-            std::cell::LazyCell::force(&precache[n_idx_idx as usize]);
+        if h < incumbent_h_score {
+            let h = key(start_h_score, h);
+            neutron_systems.advise_need(&[n_idx_idx]);
             *h_score.entry(n_idx).or_insert(h) = h;
-            to_visit.push(Reverse((h, n_idx, n_idx_idx)));
+            to_visit.push(Reverse((h, systems.id64(n_idx), n_idx, n_idx_idx)));
             *parent.entry(n_idx).or_insert(start_idx) = start_idx;
         }
     }
 
-    let mut num_processed: usize = 0;
-
-
-    while let Some(Reverse((current_h_score, current_idx, current_idx_idx))) = to_visit.pop() {
+    while let Some(Reverse((current_h_score, _, current_idx, current_idx_idx))) = to_visit.pop() {
         if current_idx == goal_idx {
             break;
         }
         if current_h_score > h_score[&current_idx] {
             continue;
         }
-
-        let current_n_system = std::cell::LazyCell::force_mut(&mut precache[current_idx_idx as usize]);
+        let state_bytes = map_bytes(&parent) + map_bytes(&g_score) + map_bytes(&h_score) + heap_bytes(&to_visit);
+        if let Err(reason) = budget.spend(state_bytes) {
+            return stopped(reason, &parent, &subpaths);
+        }
+        count_expansion(true);
 
         let parent_idx = parent[&current_idx];
-        let parent_s = systems.get(parent_idx);
-        let current = systems.get(current_idx);
         let parent_g_score = g_score[&parent_idx];
-        let from_path_len = if parent_s.is_neutron && distance(&parent_s, &current) <= jump_distance * 4.0 {
-            1
-        } else if distance(&parent_s, &current) <= jump_distance {
+        let from_path_distance = system_distance(systems, parent_idx, current_idx);
+        let from_path_len = if is_one_jump(parent_idx, current_idx) {
             1
         } else {
-            let from_path = a_star(systems, parent_idx, current_idx, jump_distance, Some(no_neutron_len - parent_g_score.jumps));
-            if from_path.is_none() {
-                continue;
+            match a_star_budgeted(systems, parent_idx, current_idx, jump_distance, incumbent_len.map(|len| len - parent_g_score.jumps), SearchMode::Fast, budget) {
+                SearchOutcome::Found(from_path) => {
+                    let len = from_path.len() as i64;
+                    subpaths.insert(current_idx, (parent_idx, from_path));
+                    len
+                }
+                SearchOutcome::NoRoute => continue,
+                SearchOutcome::Stopped { reason, .. } => return stopped(reason, &parent, &subpaths),
             }
-            let from_path = from_path.unwrap();
-            from_path.len() as i64
         };
-        let from_path_distance = distance(&parent_s, &current);
         let cur_g_score = HScore{jumps: parent_g_score.jumps + from_path_len, distance:parent_g_score.distance + from_path_distance};
-        println!("[N] Queue length: {}", to_visit.len());
-        println!("[N] Processing {} {:?}\n {:?} {:?}", current.name, current.coords, cur_g_score, current_h_score);
         *g_score.entry(current_idx).or_insert(cur_g_score) = cur_g_score;
 
-        // TODO this needs to account for the first just being from a neutron star
-        let to_goal_distance = distance(&current, &goal);
-        let to_goal_jumps = {
-            let after_first_jump_to_goal_distance = to_goal_distance - (jump_distance * 4.0);
-            if after_first_jump_to_goal_distance <= 0.0.into() {
-                1
-            } else {
-                1 + f32::from((after_first_jump_to_goal_distance / jump_distance).ceil()) as i64
-            }
-        };
-        let to_goal_h_score = HScore {
-            distance: cur_g_score.distance + to_goal_distance,
-            jumps: cur_g_score.jumps + to_goal_jumps,
-        };
+        let to_goal_h_score = key(cur_g_score, to_goal_fn(current_idx));
         if to_goal_h_score < h_score[&goal_idx] {
             *h_score.get_mut(&goal_idx).unwrap() = to_goal_h_score;
-            to_visit.push(Reverse((to_goal_h_score, goal_idx, 0)));
+            to_visit.push(Reverse((to_goal_h_score, systems.id64(goal_idx), goal_idx, 0)));
             *parent.get_mut(&goal_idx).unwrap() = current_idx;
         }
 
-        num_processed += 1;
-        if num_processed % 100_000 == 0 {
-            println!("Total processed {} ({}%)", num_processed, (num_processed as f64 * 100.0) / (neutron_systems.len() as f64));
-        }
-
-        let current_n_system = current_n_system.await.unwrap();
-        for &n_idx_idx in &current_n_system.neighbors {
-            std::cell::LazyCell::force(&precache[n_idx_idx as usize]);
-            let neighbor_idx = idx_lookup[n_idx_idx as usize];
-            let mut new_h_score = h_fn(current_idx, neighbor_idx);
-            new_h_score.jumps += cur_g_score.jumps;
-            new_h_score.distance += cur_g_score.distance;
-            if new_h_score >= no_neutron_h_score {
+        for &n_idx_idx in neutron_systems.neighbors(current_idx_idx) {
+            let neighbor_idx = neutron_systems.idx(n_idx_idx);
+            let h = h_fn(current_idx, neighbor_idx);
+            let unweighted = HScore{jumps: cur_g_score.jumps + h.jumps, distance: cur_g_score.distance + h.distance};
+            if unweighted >= incumbent_h_score {
                 continue;
             }
+            let new_h_score = key(cur_g_score, h);
             if !h_score.contains_key(&neighbor_idx) || new_h_score < h_score[&neighbor_idx] {
                 *h_score.entry(neighbor_idx).or_insert(new_h_score) = new_h_score;
-                to_visit.push(Reverse((new_h_score, neighbor_idx, n_idx_idx)));
+                neutron_systems.advise_need(&[n_idx_idx]);
+                to_visit.push(Reverse((new_h_score, systems.id64(neighbor_idx), neighbor_idx, n_idx_idx)));
                 *parent.entry(neighbor_idx).or_insert(current_idx) = current_idx;
             }
         }
     }
 
-    // println!("Path found, building...");
-    let mut path_idx = goal_idx;
-    let mut path: Vec<u32> = Vec::new();
-    let mut good = false;
-    while let Some(&parent_idx) = parent.get(&path_idx) {
-        // println!("Finding subpath {} {}", parent_idx, path_idx);
-        let subpath = a_star(systems, parent_idx, path_idx, jump_distance, None)?;
-        path.extend(subpath.into_iter().rev());
-        if parent_idx == start_idx {
-            good = true;
-            break;
-        }
-        path_idx = parent_idx;
+    match build_route(&parent, &subpaths, Some(budget)) {
+        Ok(Some(route)) => SearchOutcome::Found(route),
+        Ok(None) => SearchOutcome::NoRoute,
+        Err(reason) => SearchOutcome::Stopped { reason, best: None },
     }
-
-    if good {
-        return Some(path.into_iter().rev().collect());
-    }
-
-
-    None
 }
 
-pub fn make_neutron_star_systems(systems: &Vec<StarSystem>, max_jump_distance: f32) -> Vec<NeutronStarSystem> {
-    let max_jump_distance: Float = Float::from(max_jump_distance);
-    let neutron_stars: Vec<u32> = systems.iter().enumerate().filter_map(|(i, s)| {
-        if s.is_neutron {
-            Some(i as u32)
-        } else {
-            None
+pub const NEUTRON_MAX_DISTANCE: f32 = 5000.0;
+
+// Neighbors of one neutron star, as indexes into `neutron_stars`: everything
+// within jump range, plus farther stars that no kept neighbor is closer to.
+// Scans every neutron star; NeutronGrid gives the same lists much faster.
+pub fn neutron_neighbors(systems: &[StarSystem], neutron_stars: &[u32], start_idx: u32, max_jump_distance: Float) -> Box<[u32]> {
+    let max_distance = Float::from(NEUTRON_MAX_DISTANCE);
+    let start = &systems[start_idx as usize];
+    let mut sorted: Vec<(u32, u32)> = neutron_stars.iter().copied().enumerate().map(|(t1, t2)| (t1 as u32, t2)).filter(|&(_, i)| distance(start, &systems[i as usize]) < max_distance).collect();
+    sorted.sort_by(|&(_,i), &(_,j)| {
+        distance(start, &systems[i as usize]).cmp(&distance(start, &systems[j as usize]))
+    });
+    let mut neighbors: Vec<u32> = Vec::new();
+    for &(n_idx_idx, n_idx) in &sorted {
+        if n_idx == start_idx {
+            continue;
         }
-    }).collect();
-    let total_to_process = neutron_stars.len();
-    let mut retval = Arc::new(Mutex::new(Vec::new()));
-    retval.lock().unwrap().resize(neutron_stars.len(), NeutronStarSystem{idx: 0, neighbors: Box::new([])});
-    let MAX_DISTANCE = Float::from(5000.0);
-    //let MAX_DISTANCE_WARN = Float::from(10000.0);
-    let n_copied: Vec<(u32, u32)> = neutron_stars.iter().copied().enumerate().map(|(t1, t2)| (t1 as u32, t2)).collect();
-    let num_processed = Arc::new(Mutex::new(0));
-    n_copied.into_par_iter().for_each(|(start_n_idx, start_idx)| {
-        let start = &systems[start_idx as usize];
-        let mut sorted: Vec<(u32, u32)> = neutron_stars.iter().copied().enumerate().map(|(t1, t2)| (t1 as u32, t2)).filter(|&(_, i)| distance(start, &systems[i as usize]) < MAX_DISTANCE).collect();
-        sorted.sort_by(|&(_,i), &(_,j)| {
-            distance(start, &systems[i as usize]).partial_cmp(&distance(start, &systems[j as usize])).unwrap()
-        });
-        let mut neighbors: Vec<u32> = Vec::new();
-        for &(n_idx_idx, n_idx) in &sorted {
-            if n_idx == start_idx {
-                continue;
-            }
-            let neigh = &systems[n_idx as usize];
-            let d = distance(start, neigh);
-            if d <= max_jump_distance {
-                neighbors.push(n_idx_idx);
-            } else {
-                let mut should_add = true;
-                for &existing_idx_idx in &neighbors {
-                    let existing = &systems[neutron_stars[existing_idx_idx as usize] as usize];
-                    if d > distance(neigh, existing) {
-                        should_add = false;
-                        break;
-                    }
-                }
-                if should_add {
-                    neighbors.push(n_idx_idx);
+        let neigh = &systems[n_idx as usize];
+        let d = distance(start, neigh);
+        if d <= max_jump_distance {
+            neighbors.push(n_idx_idx);
+        } else {
+            let mut should_add = true;
+            for &existing_idx_idx in &neighbors {
+                let existing = &systems[neutron_stars[existing_idx_idx as usize] as usize];
+                if d > distance(neigh, existing) {
+                    should_add = false;
+                    break;
                 }
             }
-        }
-        let neighbors: Box<[u32]> = neighbors.into_boxed_slice();
-        {
-            let retval = &mut retval.lock().unwrap();
-            retval[start_n_idx as usize] = NeutronStarSystem{
-                idx: start_idx,
-                neighbors
-            };
-        }
-        {
-            let mut num_processed = num_processed.lock().unwrap();
-            *num_processed += 1;
-            if *num_processed % 1_000 == 0 {
-                println!("Processed {} {}%", num_processed, (*num_processed as f64 * 100.0) / total_to_process as f64);
+            if should_add {
+                neighbors.push(n_idx_idx);
             }
         }
-    });
-    Arc::into_inner(retval).unwrap().into_inner().unwrap()
+    }
+    neighbors.into_boxed_slice()
+}
+
+pub fn make_neutron_star_systems(systems: &[StarSystem], max_jump_distance: f32) -> Vec<NeutronStarSystem> {
+    make_neutron_star_systems_with_policy(systems, max_jump_distance, &PruningPolicy::default(), &SilentProgress)
+}
+
+pub fn make_neutron_star_systems_with_policy(systems: &[StarSystem], max_jump_distance: f32, policy: &PruningPolicy, progress: &dyn Progress) -> Vec<NeutronStarSystem> {
+    let grid = NeutronGrid::new(systems);
+    let total_to_process = grid.len();
+    let mut retval = Vec::with_capacity(total_to_process);
+    progress.begin("Neutron graph", Some(total_to_process as u64));
+    grid.build_chunks_with_policy(max_jump_distance, policy, |_| false, |_, chunk| -> Result<(), ()> {
+        retval.extend(chunk);
+        progress.advance(retval.len() as u64);
+        Ok(())
+    }).unwrap();
+    progress.end();
+    retval
 }
 
 pub fn write_indexed_file<T: bincode::Encode>(data: &[T], output_filepath: &str) -> std::io::Result<()> {
@@ -554,7 +963,7 @@ pub fn write_indexed_file<T: bincode::Encode>(data: &[T], output_filepath: &str)
     let mut buf_out = std::io::BufWriter::new(out_f);
     let mut offset_table: Vec<(usize, u32)> = vec![(0usize, 0u32); data.len()];
     let mut offset_table_size: usize = 0;
-    let mut current_offset = bincode::encode_into_std_write(&offset_table_size, &mut buf_out, bincode_config).unwrap();
+    let mut current_offset = bincode::encode_into_std_write(offset_table_size, &mut buf_out, bincode_config).unwrap();
     if current_offset != 8 {
         panic!("current_offset is {}", current_offset);
     }
@@ -573,40 +982,166 @@ pub fn write_indexed_file<T: bincode::Encode>(data: &[T], output_filepath: &str)
 }
 
 pub struct IndexedFileMap<T: bincode::Decode<()>> {
-    file: File,
     map: Mmap,
     offset_table: Vec<(usize, u32)>,
     phantom: std::marker::PhantomData<T>,
 }
 
-pub struct CachedIndexedFileMap<T: bincode::Decode<()>, U: From<T> + Clone> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    // Keep every decoded record for the lifetime of the map.
+    Unbounded,
+    // Keep at most `capacity` records, evicting with the CLOCK approximation of LRU.
+    Clock { capacity: usize },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct ClockSlot<U> {
+    idx: u32,
+    value: Arc<U>,
+    referenced: bool,
+}
+
+struct ClockCache<U> {
+    capacity: usize,
+    slots: Vec<ClockSlot<U>>,
+    slot_lookup: HashMap<u32, usize>,
+    hand: usize,
+}
+
+impl<U> ClockCache<U> {
+    fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("cache capacity must be at least 1");
+        }
+        Self {
+            capacity,
+            slots: Vec::with_capacity(capacity),
+            slot_lookup: HashMap::with_capacity(capacity),
+            hand: 0,
+        }
+    }
+
+    fn get(&mut self, idx: u32) -> Option<Arc<U>> {
+        let &slot_idx = self.slot_lookup.get(&idx)?;
+        let slot = &mut self.slots[slot_idx];
+        slot.referenced = true;
+        Some(slot.value.clone())
+    }
+
+    // Returns the cached value (which may have been inserted by another thread
+    // in the meantime) and whether an older entry had to be evicted.
+    fn insert(&mut self, idx: u32, value: Arc<U>) -> (Arc<U>, bool) {
+        if let Some(existing) = self.get(idx) {
+            return (existing, false);
+        }
+        if self.slots.len() < self.capacity {
+            self.slot_lookup.insert(idx, self.slots.len());
+            self.slots.push(ClockSlot{idx, value: value.clone(), referenced: true});
+            return (value, false);
+        }
+        while self.slots[self.hand].referenced {
+            self.slots[self.hand].referenced = false;
+            self.hand = (self.hand + 1) % self.capacity;
+        }
+        let victim = &mut self.slots[self.hand];
+        self.slot_lookup.remove(&victim.idx);
+        self.slot_lookup.insert(idx, self.hand);
+        *victim = ClockSlot{idx, value: value.clone(), referenced: true};
+        self.hand = (self.hand + 1) % self.capacity;
+        (value, true)
+    }
+}
+
+enum CacheStore<U> {
+    Unbounded(Vec<std::sync::OnceLock<Arc<U>>>),
+    Clock(Mutex<ClockCache<U>>),
+}
+
+pub struct CachedIndexedFileMap<T: bincode::Decode<()>, U: From<T>> {
     map: IndexedFileMap<T>,
-    cache: Vec<std::sync::OnceLock<U>>,
+    cache: CacheStore<U>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
 }
 
-impl<T: bincode::Decode<()>, U: From<T> + Clone> CachedIndexedFileMap<T, U> {
+impl<T: bincode::Decode<()>, U: From<T>> CachedIndexedFileMap<T, U> {
     pub fn new(filepath: &str) -> Self {
+        Self::with_policy(filepath, CachePolicy::Unbounded)
+    }
+
+    pub fn with_policy(filepath: &str, policy: CachePolicy) -> Self {
         let map = IndexedFileMap::new(filepath);
-        let mut cache = Vec::new();
-        cache.resize(map.len() as usize, std::sync::OnceLock::new());
+        let cache = match policy {
+            CachePolicy::Unbounded => {
+                let mut cache = Vec::new();
+                cache.resize_with(map.len() as usize, std::sync::OnceLock::new);
+                CacheStore::Unbounded(cache)
+            }
+            CachePolicy::Clock { capacity } => CacheStore::Clock(Mutex::new(ClockCache::new(capacity))),
+        };
         Self {
             map,
             cache,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
 
-    pub fn get(&self, idx: u32) -> &U {
-        self.cache[idx as usize].get_or_init(|| {
-            self.get_no_cache(idx)
-        })
+    pub fn get(&self, idx: u32) -> Arc<U> {
+        match &self.cache {
+            CacheStore::Unbounded(cache) => {
+                let cell = &cache[idx as usize];
+                if let Some(value) = cell.get() {
+                    self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+                    return value.clone();
+                }
+                self.misses.fetch_add(1, AtomicOrdering::Relaxed);
+                cell.get_or_init(|| Arc::new(self.get_no_cache(idx))).clone()
+            }
+            CacheStore::Clock(cache) => {
+                if let Some(value) = cache.lock().unwrap().get(idx) {
+                    self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+                    return value;
+                }
+                self.misses.fetch_add(1, AtomicOrdering::Relaxed);
+                // Decode without holding the lock so other readers aren't blocked
+                let value = Arc::new(self.get_no_cache(idx));
+                let (value, evicted) = cache.lock().unwrap().insert(idx, value);
+                if evicted {
+                    self.evictions.fetch_add(1, AtomicOrdering::Relaxed);
+                }
+                value
+            }
+        }
     }
 
     pub fn get_no_cache(&self, idx: u32) -> U {
-        return self.map.get(idx).into();
+        self.map.get(idx).into()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(AtomicOrdering::Relaxed),
+            misses: self.misses.load(AtomicOrdering::Relaxed),
+            evictions: self.evictions.load(AtomicOrdering::Relaxed),
+        }
     }
 
     pub fn len(&self) -> u32 {
-        return self.map.len();
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
     }
 }
 
@@ -620,7 +1155,6 @@ impl<T: bincode::Decode<()>> IndexedFileMap<T> {
         let (offset_table, _) = bincode::decode_from_slice(&map[8..offset_table_size+8], bincode_config).unwrap();
         let phantom = std::marker::PhantomData;
         Self {
-            file,
             map,
             offset_table,
             phantom,
@@ -650,12 +1184,299 @@ impl<T: bincode::Decode<()>> IndexedFileMap<T> {
     pub fn len(&self) -> u32 {
         self.offset_table.len() as u32
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset_table.is_empty()
+    }
+}
+
+// Neutron file layout (little endian), every section naturally aligned so the
+// mmap can be read in place:
+//   magic [u8; 8], count u64, total_neighbors u64
+//   offsets [u64; count + 1]  (start of each neighbor list, in u32 units)
+//   id64 [u64; count]
+//   idx [u32; count]
+//   neighbors [u32; total_neighbors]
+const NEUTRON_FILE_MAGIC: &[u8; 8] = b"NSNEIGH2";
+const NEUTRON_FILE_HEADER_SIZE: usize = 24;
+
+pub fn write_neutron_file(data: &[NeutronStarSystem], output_filepath: &str) -> std::io::Result<()> {
+    let stars = data.iter().map(|n| (n.neighbors.len(), n.id64, n.idx));
+    write_neutron_sections(output_filepath, stars, |out| {
+        data.iter().try_for_each(|n| write_neighbor_list(out, &n.neighbors))
+    })?;
+    Ok(())
+}
+
+// Writes a neutron file given (neighbor count, id64, idx) for every neutron
+// star, walking `stars` once per section. `write_neighbors` then appends the
+// neighbor lists in the same order with `write_neighbor_list`. Returns the
+// number of neutron stars.
+pub(crate) fn write_neutron_sections<I, F>(output_filepath: &str, stars: I, write_neighbors: F) -> std::io::Result<usize>
+where
+    I: Iterator<Item = (usize, u64, u32)> + Clone,
+    F: FnOnce(&mut std::io::BufWriter<File>) -> std::io::Result<()>,
+{
+    use std::io::Write;
+    let out_f = File::create(output_filepath)?;
+    let mut buf_out = std::io::BufWriter::new(out_f);
+    let count = stars.clone().count();
+    let total_neighbors: u64 = stars.clone().map(|(len, _, _)| len as u64).sum();
+    buf_out.write_all(NEUTRON_FILE_MAGIC)?;
+    buf_out.write_all(&(count as u64).to_le_bytes())?;
+    buf_out.write_all(&total_neighbors.to_le_bytes())?;
+    let mut offset: u64 = 0;
+    for (len, _, _) in stars.clone() {
+        buf_out.write_all(&offset.to_le_bytes())?;
+        offset += len as u64;
+    }
+    buf_out.write_all(&offset.to_le_bytes())?;
+    for (_, id64, _) in stars.clone() {
+        buf_out.write_all(&id64.to_le_bytes())?;
+    }
+    for (_, _, idx) in stars {
+        buf_out.write_all(&idx.to_le_bytes())?;
+    }
+    write_neighbors(&mut buf_out)?;
+    buf_out.flush()?;
+    Ok(count)
+}
+
+pub(crate) fn write_neighbor_list(out: &mut impl std::io::Write, neighbors: &[u32]) -> std::io::Result<()> {
+    for &neighbor in neighbors {
+        out.write_all(&neighbor.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub struct NeutronFileMap {
+    map: Mmap,
+    count: usize,
+    total_neighbors: usize,
+    id64_start: usize,
+    idx_start: usize,
+    neighbors_start: usize,
+    // Set when the file was cooked against a different system ordering
+    remapped_idx: Option<Box<[u32]>>,
+}
+
+impl NeutronFileMap {
+    pub fn new(filepath: &str) -> Self {
+        if cfg!(target_endian = "big") {
+            panic!("neutron files are only readable in place on little endian targets");
+        }
+        let file = File::open(filepath).unwrap();
+        let map = unsafe { Mmap::map(&file).unwrap() };
+        map.advise(memmap2::Advice::Random).unwrap();
+        if map.len() < NEUTRON_FILE_HEADER_SIZE || &map[..8] != NEUTRON_FILE_MAGIC {
+            panic!("{} is not a neutron file (or was written by an older version)", filepath);
+        }
+        let count = u64::from_le_bytes(map[8..16].try_into().unwrap()) as usize;
+        let total_neighbors = u64::from_le_bytes(map[16..24].try_into().unwrap()) as usize;
+        let sections = (|| {
+            let id64_start = count.checked_add(1)?.checked_mul(8)?.checked_add(NEUTRON_FILE_HEADER_SIZE)?;
+            let idx_start = count.checked_mul(8)?.checked_add(id64_start)?;
+            let neighbors_start = count.checked_mul(4)?.checked_add(idx_start)?;
+            let end = total_neighbors.checked_mul(4)?.checked_add(neighbors_start)?;
+            Some((id64_start, idx_start, neighbors_start, end))
+        })();
+        let Some((id64_start, idx_start, neighbors_start, end)) = sections else {
+            panic!("{} is corrupt: {} neutron stars with {} neighbors don't fit in a file", filepath, count, total_neighbors);
+        };
+        if map.len() != end {
+            panic!("{} is truncated or corrupt", filepath);
+        }
+        // mmaps are page aligned, so the section offsets above are enough for alignment
+        assert_eq!(map.as_ptr().align_offset(8), 0);
+        let neutron_map = Self {
+            map,
+            count,
+            total_neighbors,
+            id64_start,
+            idx_start,
+            neighbors_start,
+            remapped_idx: None,
+        };
+        // The sections are read in place without further checks, so a bad
+        // offset or neighbor would index out of bounds later
+        let offsets = neutron_map.offsets();
+        if offsets[0] != 0 || offsets[count] != total_neighbors as u64 || offsets.windows(2).any(|w| w[0] > w[1]) {
+            panic!("{} is corrupt: neighbor list offsets are out of order", filepath);
+        }
+        if neutron_map.all_neighbors().par_iter().any(|&n_idx| n_idx as usize >= count) {
+            panic!("{} is corrupt: a neighbor is out of range", filepath);
+        }
+        neutron_map
+    }
+
+    fn offsets(&self) -> &[u64] {
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(NEUTRON_FILE_HEADER_SIZE) as *const u64, self.count + 1) }
+    }
+
+    fn all_neighbors(&self) -> &[u32] {
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(self.neighbors_start) as *const u32, self.total_neighbors) }
+    }
+
+    fn id64s(&self) -> &[u64] {
+        unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(self.id64_start) as *const u64, self.count) }
+    }
+
+    fn idxs(&self) -> &[u32] {
+        match &self.remapped_idx {
+            Some(remapped_idx) => remapped_idx,
+            None => unsafe { std::slice::from_raw_parts(self.map.as_ptr().add(self.idx_start) as *const u32, self.count) },
+        }
+    }
+
+    pub fn idx(&self, n_idx: u32) -> u32 {
+        self.idxs()[n_idx as usize]
+    }
+
+    pub fn id64(&self, n_idx: u32) -> u64 {
+        self.id64s()[n_idx as usize]
+    }
+
+    pub fn neighbors(&self, n_idx: u32) -> &[u32] {
+        let offsets = self.offsets();
+        let start = offsets[n_idx as usize] as usize;
+        let end = offsets[n_idx as usize + 1] as usize;
+        &self.all_neighbors()[start..end]
+    }
+
+    pub fn get(&self, n_idx: u32) -> NeutronStarSystem {
+        NeutronStarSystem {
+            idx: self.idx(n_idx),
+            id64: self.id64(n_idx),
+            neighbors: self.neighbors(n_idx).into(),
+        }
+    }
+
+    // Points every record at the system with the same id64 in `systems`, so a
+    // neutron file keeps working after the system list is re-sorted or
+    // refreshed. Cheap when nothing moved. Returns how many records changed.
+    pub fn remap<S: Systems>(&mut self, systems: &S) -> std::io::Result<usize> {
+        let idxs = self.idxs();
+        let id64s = self.id64s();
+        let stale: Vec<u32> = (0..self.count as u32).into_par_iter().filter(|&n_idx| {
+            let idx = idxs[n_idx as usize];
+            idx >= systems.len() || systems.id64(idx) != id64s[n_idx as usize]
+        }).collect();
+        if stale.is_empty() {
+            return Ok(0);
+        }
+        let invalid = |msg: String| Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg));
+        let mut n_idx_by_id64: HashMap<u64, u32> = stale.iter().map(|&n_idx| (id64s[n_idx as usize], n_idx)).collect();
+        if n_idx_by_id64.len() != stale.len() {
+            return invalid("neutron file has duplicate id64s".to_string());
+        }
+        let found: Vec<(u32, u32)> = (0..systems.len()).into_par_iter().filter_map(|idx| {
+            n_idx_by_id64.get(&systems.id64(idx)).map(|&n_idx| (n_idx, idx))
+        }).collect();
+        let mut remapped_idx: Box<[u32]> = idxs.into();
+        for (n_idx, idx) in found {
+            remapped_idx[n_idx as usize] = idx;
+            n_idx_by_id64.remove(&id64s[n_idx as usize]);
+        }
+        if let Some((id64, _)) = n_idx_by_id64.iter().next() {
+            return invalid(format!("{} neutron stars (e.g. id64 {}) are missing from the systems; re-cook the neutron file", n_idx_by_id64.len(), id64));
+        }
+        self.remapped_idx = Some(remapped_idx);
+        Ok(stale.len())
+    }
+
+    pub fn is_remapped(&self) -> bool {
+        self.remapped_idx.is_some()
+    }
+
+    pub fn advise_need(&self, n_idxs: &[u32]) {
+        let offsets = self.offsets();
+        for &i in n_idxs {
+            let start = offsets[i as usize] as usize;
+            let end = offsets[i as usize + 1] as usize;
+            if start == end {
+                continue;
+            }
+            self.map.advise_range(
+                memmap2::Advice::WillNeed,
+                self.neighbors_start + start * 4,
+                (end - start) * 4
+            ).unwrap();
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.count as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
 }
 
 pub struct VecMap<T> {
     v: Box<[T]>
 }
 
+impl Systems for VecMap<StarSystem> {
+    fn len(&self) -> u32 {
+        self.v.len() as u32
+    }
+
+    fn coords(&self, idx: u32) -> V3 {
+        self.get(idx).coords
+    }
+
+    fn distance_from_sol(&self, idx: u32) -> Float {
+        self.get(idx).distance_from_sol
+    }
+
+    fn is_neutron(&self, idx: u32) -> bool {
+        self.get(idx).is_neutron
+    }
+
+    fn name(&self, idx: u32) -> &str {
+        &self.get(idx).name
+    }
+
+    fn main_star_type(&self, idx: u32) -> &str {
+        &self.get(idx).main_star_type
+    }
+
+    fn id64(&self, idx: u32) -> u64 {
+        self.get(idx).id64
+    }
+}
+
+impl Systems for [StarSystem] {
+    fn len(&self) -> u32 {
+        <[StarSystem]>::len(self) as u32
+    }
+
+    fn coords(&self, idx: u32) -> V3 {
+        self[idx as usize].coords
+    }
+
+    fn distance_from_sol(&self, idx: u32) -> Float {
+        self[idx as usize].distance_from_sol
+    }
+
+    fn is_neutron(&self, idx: u32) -> bool {
+        self[idx as usize].is_neutron
+    }
+
+    fn name(&self, idx: u32) -> &str {
+        &self[idx as usize].name
+    }
+
+    fn main_star_type(&self, idx: u32) -> &str {
+        &self[idx as usize].main_star_type
+    }
+
+    fn id64(&self, idx: u32) -> u64 {
+        self[idx as usize].id64
+    }
+}
+
 impl<T> VecMap<T> {
     pub fn new(v: Vec<T>) -> Self {
         Self {
@@ -670,4 +1491,8 @@ impl<T> VecMap<T> {
     pub fn len(&self) -> u32 {
         self.v.len() as u32
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
 }
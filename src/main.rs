@@ -1,15 +1,18 @@
 use std::env;
-use neutron_route_finder::{neutron_a_star, IndexedFileMap, CachedIndexedFileMap, StarSystemRecord, StarSystem, read_star_systems_bincode, VecMap};
-use std::sync::{Arc, Mutex};
-use rayon::prelude::*;
+use std::time::{Duration, Instant};
+use neutron_route_finder::{find_systems_by_name, neutron_a_star_anytime, neutron_a_star_with_options, open_data_dir, Float, SearchMode, SearchOptions, ProgressStyle, SearchOutcome, Systems, ANYTIME_WEIGHTS};
 
-#[tokio::main]
-async fn main() {
+fn main() {
     let args: Vec<String> = env::args().collect();
-    let systems: Vec<StarSystem> = read_star_systems_bincode(&args[1], |_| true);
-    let systems = VecMap::new(systems);
-    let neutron_systems = Arc::new(IndexedFileMap::new(&args[2]));
+    let (manifest, systems, neutron_systems) = open_data_dir(&args[1]).unwrap();
+    let mode = if args.iter().any(|a| a == "--exact") { SearchMode::Exact } else { SearchMode::Fast };
+    // --time-limit SECONDS: settle for the best route found by then
+    let deadline = args.iter().position(|a| a == "--time-limit").map(|i| {
+        Instant::now() + Duration::from_secs_f64(args.get(i + 1).expect("--time-limit needs a value").parse().unwrap())
+    });
     println!("Read {} systems", systems.len());
+    let jump_distance = Float::from(63.0);
+    manifest.check_jump_distance(jump_distance).unwrap();
 
     //let start_name = "Sol";
     //let goal_name = "Colonia";
@@ -26,39 +29,37 @@ async fn main() {
     //let goal_name = "Traikee IY-U c2-4";
     //let start_name = "NGC 2546 Sector AO-V b33-0";
     //let goal_name = "NGC 2546 Sector KH-B b17-2";
-    let (start_idx, goal_idx) = {
-        let mut start_idx = Arc::new(Mutex::new(None));
-        let mut goal_idx = Arc::new(Mutex::new(None));
-        (0..systems.len()).par_bridge().find_map_any(|idx| {
-            let system = systems.get(idx);
-            if system.name == start_name {
-                let mut start_idx = start_idx.lock().unwrap();
-                let mut goal_idx = goal_idx.lock().unwrap();
-                *start_idx = Some(idx);
-                if start_idx.is_some() && goal_idx.is_some() {
-                    return Some(());
-                }
-            }
-            if system.name == goal_name {
-                let mut start_idx = start_idx.lock().unwrap();
-                let mut goal_idx = goal_idx.lock().unwrap();
-                *goal_idx = Some(idx);
-                if start_idx.is_some() && goal_idx.is_some() {
-                    return Some(());
-                }
-            }
-            None
-        }).unwrap();
-        (start_idx.lock().unwrap().unwrap(), goal_idx.lock().unwrap().unwrap())
+    let (start_idx, goal_idx) = match find_systems_by_name(&systems, &[start_name, goal_name])[..] {
+        [Some(start_idx), Some(goal_idx)] => (start_idx, goal_idx),
+        _ => panic!("Couldn't find {} or {}", start_name, goal_name),
     };
-                
     println!("Start: {}, Goal: {}", start_idx, goal_idx);
     //let start_idx = 0;
     //let goal_idx = {
         
     //};
 
-    let path = neutron_a_star(&systems, &neutron_systems, start_idx, goal_idx, 63.0.into()).await.unwrap();
+    // --progress bar|log|none
+    let progress = args.iter().position(|a| a == "--progress").map_or(ProgressStyle::Bar, |i| {
+        args.get(i + 1).expect("--progress needs a style").parse().unwrap()
+    });
+    let options = SearchOptions { mode, deadline, progress: Some(progress.reporter()), ..SearchOptions::default() };
+    // --anytime: print each better route as it's found
+    let outcome = if args.iter().any(|a| a == "--anytime") {
+        neutron_a_star_anytime(&systems, &neutron_systems, start_idx, goal_idx, jump_distance, &ANYTIME_WEIGHTS, &options, |found| {
+            println!("Found {} jumps (weight {}, at most {:.2}x the fewest)", found.route.len(), found.weight, found.bound);
+        })
+    } else {
+        neutron_a_star_with_options(&systems, &neutron_systems, start_idx, goal_idx, jump_distance, &options)
+    };
+    let path = match outcome {
+        SearchOutcome::Found(path) => path,
+        SearchOutcome::NoRoute => panic!("No route"),
+        SearchOutcome::Stopped { reason, best } => {
+            println!("Search stopped ({}), best route so far:", reason);
+            best.expect("No route found before stopping")
+        }
+    };
     let path_len = path.len();
     for system_idx in path {
         let system = systems.get(system_idx);
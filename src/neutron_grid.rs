@@ -0,0 +1,159 @@
+use rayon::prelude::*;
+use crate::{distance_v, Float, NeutronStarSystem, PruningPolicy, PruningRule, Systems, V3, NEUTRON_MAX_DISTANCE};
+use crate::pruning::Pruner;
+
+// Cells are small enough that the cube searched around a star is mostly inside
+// the NEUTRON_MAX_DISTANCE sphere, and big enough to keep the cell list short.
+const GRID_CELL_SIZE: f32 = NEUTRON_MAX_DISTANCE / 8.0;
+pub const NEUTRON_CHUNK_SIZE: usize = 10_000;
+
+type CellKey = (i32, i32, i32);
+
+fn cell_key(coords: &V3) -> CellKey {
+    let key = |c: Float| (f32::from(c) / GRID_CELL_SIZE).floor() as i32;
+    (key(coords.0), key(coords.1), key(coords.2))
+}
+
+// The neutron stars of a galaxy bucketed into cubic cells, so neighbor
+// candidates come from nearby cells instead of a scan of every neutron star.
+// Holds only what the graph needs (no names), so it stays small next to the
+// full system list.
+pub struct NeutronGrid {
+    // Per neutron star, in neutron list order
+    idxs: Vec<u32>,
+    id64s: Vec<u64>,
+    coords: Vec<V3>,
+    // Neutron list indexes grouped by cell; cell i holds
+    // members[cell_starts[i]..cell_starts[i + 1]]
+    cell_keys: Vec<CellKey>,
+    cell_starts: Vec<u32>,
+    members: Vec<u32>,
+}
+
+impl NeutronGrid {
+    pub fn new<S: Systems + ?Sized>(systems: &S) -> Self {
+        let idxs: Vec<u32> = (0..systems.len()).into_par_iter().filter(|&idx| systems.is_neutron(idx)).collect();
+        let id64s: Vec<u64> = idxs.par_iter().map(|&idx| systems.id64(idx)).collect();
+        let coords: Vec<V3> = idxs.par_iter().map(|&idx| systems.coords(idx)).collect();
+        let mut members: Vec<u32> = (0..idxs.len() as u32).collect();
+        members.par_sort_by_key(|&n_idx_idx| (cell_key(&coords[n_idx_idx as usize]), n_idx_idx));
+        let mut cell_keys = Vec::new();
+        let mut cell_starts = Vec::new();
+        for (i, &n_idx_idx) in members.iter().enumerate() {
+            let key = cell_key(&coords[n_idx_idx as usize]);
+            if cell_keys.last() != Some(&key) {
+                cell_keys.push(key);
+                cell_starts.push(i as u32);
+            }
+        }
+        cell_starts.push(members.len() as u32);
+        Self {
+            idxs,
+            id64s,
+            coords,
+            cell_keys,
+            cell_starts,
+            members,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.idxs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.idxs.is_empty()
+    }
+
+    pub fn idx(&self, n_idx_idx: u32) -> u32 {
+        self.idxs[n_idx_idx as usize]
+    }
+
+    pub fn id64(&self, n_idx_idx: u32) -> u64 {
+        self.id64s[n_idx_idx as usize]
+    }
+
+    pub fn coords(&self, n_idx_idx: u32) -> &V3 {
+        &self.coords[n_idx_idx as usize]
+    }
+
+    // Calls `f` for every neutron star in a cell that could be within
+    // `radius` of `center`
+    fn for_each_near(&self, center: &V3, radius: f32, mut f: impl FnMut(u32)) {
+        let offset = |c: Float, sign: f32| Float::from(f32::from(c) + sign * radius);
+        let lo = cell_key(&(offset(center.0, -1.0), offset(center.1, -1.0), offset(center.2, -1.0)));
+        let hi = cell_key(&(offset(center.0, 1.0), offset(center.1, 1.0), offset(center.2, 1.0)));
+        // One extra cell each way guards against rounding at cell borders
+        for x in (lo.0 - 1)..=(hi.0 + 1) {
+            for y in (lo.1 - 1)..=(hi.1 + 1) {
+                let first = self.cell_keys.partition_point(|&key| key < (x, y, lo.2 - 1));
+                for cell in first..self.cell_keys.len() {
+                    if self.cell_keys[cell] > (x, y, hi.2 + 1) {
+                        break;
+                    }
+                    let members = &self.members[self.cell_starts[cell] as usize..self.cell_starts[cell + 1] as usize];
+                    members.iter().for_each(|&n_idx_idx| f(n_idx_idx));
+                }
+            }
+        }
+    }
+
+    // With the default policy, the same lists in the same order as
+    // `neutron_neighbors`. Candidates are sorted by distance, ties by neutron
+    // list order.
+    pub fn neighbors(&self, n_idx_idx: u32, max_jump_distance: Float) -> Box<[u32]> {
+        self.pruned_neighbors(&Pruner::new(PruningRule::Occlusion), NEUTRON_MAX_DISTANCE, n_idx_idx, max_jump_distance)
+    }
+
+    fn pruned_neighbors(&self, pruner: &Pruner, max_distance: f32, n_idx_idx: u32, max_jump_distance: Float) -> Box<[u32]> {
+        let start = self.coords(n_idx_idx);
+        let mut sorted: Vec<(Float, u32)> = Vec::new();
+        self.for_each_near(start, max_distance, |other| {
+            let d = distance_v(start, self.coords(other));
+            if d < Float::from(max_distance) && other != n_idx_idx {
+                sorted.push((d, other));
+            }
+        });
+        sorted.sort();
+        pruner.prune(self, n_idx_idx, &sorted, max_jump_distance).into_boxed_slice()
+    }
+
+    pub fn neutron_star_system(&self, n_idx_idx: u32, max_jump_distance: Float, policy: &PruningPolicy) -> NeutronStarSystem {
+        NeutronStarSystem {
+            idx: self.idx(n_idx_idx),
+            id64: self.id64(n_idx_idx),
+            neighbors: self.pruned_neighbors(&Pruner::new(policy.rule), policy.max_distance, n_idx_idx, max_jump_distance),
+        }
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.len().div_ceil(NEUTRON_CHUNK_SIZE)
+    }
+
+    // Builds the graph NEUTRON_CHUNK_SIZE stars at a time, each chunk in
+    // parallel, handing finished chunks to `sink` in order. Only one chunk of
+    // neighbor lists is held at once, and chunks for which `is_done` returns
+    // true are skipped, so an interrupted build can pick up where it stopped.
+    pub fn build_chunks<E>(&self, max_jump_distance: f32, is_done: impl Fn(usize) -> bool, sink: impl FnMut(usize, Vec<NeutronStarSystem>) -> Result<(), E>) -> Result<(), E> {
+        self.build_chunks_with_policy(max_jump_distance, &PruningPolicy::default(), is_done, sink)
+    }
+
+    pub fn build_chunks_with_policy<E>(&self, max_jump_distance: f32, policy: &PruningPolicy, is_done: impl Fn(usize) -> bool, mut sink: impl FnMut(usize, Vec<NeutronStarSystem>) -> Result<(), E>) -> Result<(), E> {
+        let max_jump_distance = Float::from(max_jump_distance);
+        let pruner = Pruner::new(policy.rule);
+        for chunk in 0..self.chunk_count() {
+            if is_done(chunk) {
+                continue;
+            }
+            let start = chunk * NEUTRON_CHUNK_SIZE;
+            let end = (start + NEUTRON_CHUNK_SIZE).min(self.len());
+            let neutrons: Vec<NeutronStarSystem> = (start as u32..end as u32).into_par_iter().map(|n_idx_idx| NeutronStarSystem {
+                idx: self.idx(n_idx_idx),
+                id64: self.id64(n_idx_idx),
+                neighbors: self.pruned_neighbors(&pruner, policy.max_distance, n_idx_idx, max_jump_distance),
+            }).collect();
+            sink(chunk, neutrons)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use crate::{ColumnarSystems, check_unique_id64s, DatasetDelta, DeltaSummary, Float, NeutronFileMap, Progress, StarSystem, StarSystemRecord, Systems, apply_delta, dataset_hash, import_star_systems_sqlite, is_json_lines_file, build_neutron_file_checkpointed, NeutronGrid, PruningPolicy, read_star_system_records_bincode, read_star_system_records_csv, read_star_system_records_json, read_star_systems_bincode, read_star_systems_sqlite, sort_star_system_records, update_neutron_star_systems, write_columnar_systems, write_neutron_file, write_star_system_records_csv, write_star_system_records_json, write_star_systems_bincode};
+
+pub const SYSTEMS_BINCODE_FILE: &str = "systems.bin.gz";
+pub const SYSTEMS_COLUMNAR_FILE: &str = "systems.col";
+pub const NEUTRONS_FILE: &str = "neutrons.nbr";
+pub const SYSTEMS_SQLITE_FILE: &str = "systems.sqlite";
+pub const MANIFEST_FILE: &str = "manifest.json";
+// Checkpointed chunks of an unfinished neutron graph build
+pub const NEUTRON_CHUNKS_DIR: &str = "neutrons.chunks";
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestFile {
+    pub stage: String,
+    pub path: String,
+    pub size: u64,
+    // dataset_hash of the systems the file was made from
+    pub dataset_hash: u64,
+    // Everything besides the input files that the output depends on
+    pub params: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub source: String,
+    pub max_jump_distance: f32,
+    // Neutron graph pruning rule and candidate cutoff
+    pub pruning: String,
+    pub max_neighbor_distance: f32,
+    pub system_count: u64,
+    pub neutron_count: u64,
+    pub dataset_hash: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    pub fn read(data_dir: &str) -> std::io::Result<Manifest> {
+        let f = File::open(Path::new(data_dir).join(MANIFEST_FILE))?;
+        serde_json::from_reader(std::io::BufReader::new(f)).map_err(std::io::Error::other)
+    }
+
+    pub fn write(&self, data_dir: &str) -> std::io::Result<()> {
+        let path = Path::new(data_dir).join(MANIFEST_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        serde_json::to_writer_pretty(File::create(&tmp_path)?, self).map_err(std::io::Error::other)?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    pub fn pruning_policy(&self) -> std::io::Result<PruningPolicy> {
+        let rule = self.pruning.parse().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(PruningPolicy { rule, max_distance: self.max_neighbor_distance })
+    }
+
+    pub fn file(&self, stage: &str) -> Option<&ManifestFile> {
+        self.files.iter().find(|f| f.stage == stage)
+    }
+
+    // Checks that the loaded files are the ones this manifest describes and
+    // that the neutron graph indexes into these systems.
+    pub fn verify(&self, data_dir: &str, systems: &ColumnarSystems, neutrons: &NeutronFileMap) -> std::io::Result<()> {
+        let invalid = |msg: String| Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", data_dir, msg)));
+        for stage in ["columnar", "neutrons"] {
+            let Some(file) = self.file(stage) else {
+                return invalid(format!("manifest has no {} file", stage));
+            };
+            let size = std::fs::metadata(Path::new(data_dir).join(&file.path))?.len();
+            if size != file.size {
+                return invalid(format!("{} is {} bytes, manifest says {}", file.path, size, file.size));
+            }
+            // A remapped neutron file was cooked against another ordering of
+            // these systems, the id64 checks below vouch for it
+            if file.dataset_hash != self.dataset_hash && !(stage == "neutrons" && neutrons.is_remapped()) {
+                return invalid(format!("{} was cooked from dataset {:016x}, not {:016x}", file.path, file.dataset_hash, self.dataset_hash));
+            }
+        }
+        if systems.len() as u64 != self.system_count {
+            return invalid(format!("{} systems, manifest says {}", systems.len(), self.system_count));
+        }
+        if neutrons.len() as u64 != self.neutron_count {
+            return invalid(format!("{} neutron stars, manifest says {}", neutrons.len(), self.neutron_count));
+        }
+        let hash = dataset_hash((0..systems.len()).map(|idx| (systems.id64(idx), systems.coords(idx))));
+        if hash != self.dataset_hash {
+            return invalid(format!("systems hash to {:016x}, manifest says {:016x}", hash, self.dataset_hash));
+        }
+        let mismatch = (0..neutrons.len()).find(|&n_idx| {
+            let idx = neutrons.idx(n_idx);
+            idx >= systems.len() || !systems.is_neutron(idx) || systems.id64(idx) != neutrons.id64(n_idx)
+        });
+        if let Some(n_idx) = mismatch {
+            return invalid(format!("neutron star {} (id64 {}) doesn't match system {}", n_idx, neutrons.id64(n_idx), neutrons.idx(n_idx)));
+        }
+        Ok(())
+    }
+
+    // The graph only keeps neighbors within the cooked jump range, so a
+    // supercharged jump longer than that could miss edges.
+    pub fn check_jump_distance(&self, jump_distance: Float) -> std::io::Result<()> {
+        let boosted = f32::from(jump_distance) * 4.0;
+        if boosted > self.max_jump_distance {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("a {} ly supercharged jump is longer than the {} ly the neutron graph was cooked for", boosted, self.max_jump_distance)));
+        }
+        Ok(())
+    }
+}
+
+// Opens a cooked data directory, refusing files that don't belong together
+pub fn open_data_dir(data_dir: &str) -> std::io::Result<(Manifest, ColumnarSystems, NeutronFileMap)> {
+    let manifest = Manifest::read(data_dir)?;
+    let systems = ColumnarSystems::new(Path::new(data_dir).join(SYSTEMS_COLUMNAR_FILE).to_str().unwrap());
+    let mut neutrons = NeutronFileMap::new(Path::new(data_dir).join(NEUTRONS_FILE).to_str().unwrap());
+    // The systems moved around since the neutron file was cooked: follow its
+    // stars by id64 rather than re-cooking it
+    if manifest.file("neutrons").is_some_and(|file| file.dataset_hash != manifest.dataset_hash) {
+        neutrons.remap(&systems)?;
+    }
+    manifest.verify(data_dir, &systems, &neutrons)?;
+    Ok((manifest, systems, neutrons))
+}
+
+#[derive(Debug, Clone)]
+pub struct CookOptions {
+    pub input: String,
+    pub data_dir: String,
+    pub max_jump_distance: f32,
+    pub pruning: PruningPolicy,
+    pub sqlite: bool,
+    pub force: bool,
+    pub progress: Arc<dyn Progress>,
+}
+
+pub fn read_star_system_records(filename: &str) -> Vec<StarSystemRecord> {
+    if filename.ends_with(".csv") {
+        read_star_system_records_csv(filename)
+    } else if is_json_lines_file(filename) {
+        read_star_system_records_json(filename)
+    } else if filename.ends_with(".sqlite") || filename.ends_with(".db") {
+        read_star_systems_sqlite(filename, |_| true).unwrap().into_iter().map(|s| s.into()).collect()
+    } else {
+        read_star_system_records_bincode(filename)
+    }
+}
+
+// Writes in the format `read_star_system_records` picks for the filename
+pub fn write_star_system_records(records: &[StarSystemRecord], filename: &str) -> std::io::Result<()> {
+    if filename.ends_with(".csv") {
+        write_star_system_records_csv(records, filename)
+    } else if is_json_lines_file(filename) {
+        write_star_system_records_json(records, filename)
+    } else if filename.ends_with(".sqlite") || filename.ends_with(".db") {
+        let mut systems: Vec<StarSystem> = records.iter().cloned().map(|r| r.into()).collect();
+        systems.sort();
+        import_star_systems_sqlite(&systems, filename).map_err(std::io::Error::other)
+    } else {
+        write_star_systems_bincode(records, filename)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+struct Stage<'a> {
+    name: &'static str,
+    output: PathBuf,
+    inputs: Vec<PathBuf>,
+    params: String,
+    previous: Option<&'a ManifestFile>,
+}
+
+impl Stage<'_> {
+    // Up to date when the output is newer than every input and was made with
+    // the same parameters
+    fn is_up_to_date(&self) -> bool {
+        let Some(output_time) = modified(&self.output) else {
+            return false;
+        };
+        let inputs_older = self.inputs.iter().all(|input| modified(input).map(|t| t <= output_time).unwrap_or(false));
+        let same_params = self.previous.map(|p| p.params == self.params).unwrap_or(false);
+        inputs_older && same_params
+    }
+
+    fn record(&self, dataset_hash: u64) -> ManifestFile {
+        ManifestFile {
+            stage: self.name.to_string(),
+            path: self.output.file_name().unwrap().to_string_lossy().into_owned(),
+            size: std::fs::metadata(&self.output).map(|m| m.len()).unwrap_or(0),
+            dataset_hash,
+            params: self.params.clone(),
+        }
+    }
+}
+
+// Runs raw dump -> sorted bincode systems -> columnar systems + neutron graph
+// (+ SQLite catalogue), skipping stages whose outputs are current.
+pub fn cook(options: &CookOptions) -> std::io::Result<Manifest> {
+    std::fs::create_dir_all(&options.data_dir)?;
+    let data_dir = Path::new(&options.data_dir);
+    let previous = Manifest::read(&options.data_dir).unwrap_or_default();
+    let input = PathBuf::from(&options.input);
+    let systems_file = data_dir.join(SYSTEMS_BINCODE_FILE);
+    let mut manifest = Manifest {
+        source: options.input.clone(),
+        max_jump_distance: options.max_jump_distance,
+        pruning: options.pruning.rule.to_string(),
+        max_neighbor_distance: options.pruning.max_distance,
+        system_count: previous.system_count,
+        neutron_count: previous.neutron_count,
+        dataset_hash: previous.dataset_hash,
+        files: Vec::new(),
+    };
+
+    let import = Stage {
+        name: "import",
+        output: systems_file.clone(),
+        inputs: vec![input.clone()],
+        params: format!("source={}", options.input),
+        previous: previous.file("import"),
+    };
+    if options.force || !import.is_up_to_date() {
+        options.progress.note(&format!("[import] {} -> {}", options.input, systems_file.display()));
+        let mut records = read_star_system_records(&options.input);
+        sort_star_system_records(&mut records);
+        check_unique_id64s(&records)?;
+        manifest.system_count = records.len() as u64;
+        let tmp_file = systems_file.with_extension("gz.tmp");
+        write_star_systems_bincode(&records, tmp_file.to_str().unwrap())?;
+        std::fs::rename(tmp_file, &systems_file)?;
+    } else {
+        options.progress.note("[import] up to date");
+    }
+    manifest.files.push(import.record(previous.dataset_hash));
+    // Saved after every stage, so a rerun after a failure skips the finished ones
+    manifest.write(&options.data_dir)?;
+
+    let mut later_stages = vec![
+        Stage {
+            name: "columnar",
+            output: data_dir.join(SYSTEMS_COLUMNAR_FILE),
+            inputs: vec![systems_file.clone()],
+            params: String::new(),
+            previous: previous.file("columnar"),
+        },
+        Stage {
+            name: "neutrons",
+            output: data_dir.join(NEUTRONS_FILE),
+            inputs: vec![systems_file.clone()],
+            params: format!("max_jump_distance={} pruning={}", options.max_jump_distance, options.pruning),
+            previous: previous.file("neutrons"),
+        },
+    ];
+    if options.sqlite {
+        later_stages.push(Stage {
+            name: "sqlite",
+            output: data_dir.join(SYSTEMS_SQLITE_FILE),
+            inputs: vec![systems_file.clone()],
+            params: String::new(),
+            previous: previous.file("sqlite"),
+        });
+    }
+
+    let mut systems: Option<Vec<StarSystem>> = None;
+    for stage in &later_stages {
+        if !options.force && stage.is_up_to_date() {
+            options.progress.note(&format!("[{}] up to date", stage.name));
+            manifest.files.push(stage.record(stage.previous.map(|p| p.dataset_hash).unwrap_or(0)));
+            continue;
+        }
+        let systems = systems.get_or_insert_with(|| read_star_systems_bincode(systems_file.to_str().unwrap(), |_| true));
+        manifest.system_count = systems.len() as u64;
+        manifest.dataset_hash = dataset_hash(systems.iter().map(|s| (s.id64, s.coords)));
+        // The import stage's hash is only known once the systems have been read
+        manifest.files[0].dataset_hash = manifest.dataset_hash;
+        // Written aside and renamed so an interrupted stage never looks finished
+        let output = format!("{}.tmp", stage.output.display());
+        let output = output.as_str();
+        options.progress.note(&format!("[{}] -> {}", stage.name, stage.output.display()));
+        match stage.name {
+            "columnar" => write_columnar_systems(systems, output)?,
+            "neutrons" => {
+                let grid = NeutronGrid::new(systems.as_slice());
+                let key = format!("{:016x} {}", manifest.dataset_hash, stage.params);
+                let chunk_dir = data_dir.join(NEUTRON_CHUNKS_DIR);
+                manifest.neutron_count = build_neutron_file_checkpointed(&grid, options.max_jump_distance, &options.pruning, &key, chunk_dir.to_str().unwrap(), output, options.progress.as_ref())? as u64;
+            }
+            "sqlite" => import_star_systems_sqlite(systems, output).map_err(std::io::Error::other)?,
+            _ => unreachable!(),
+        }
+        std::fs::rename(output, &stage.output)?;
+        manifest.files.push(stage.record(manifest.dataset_hash));
+        manifest.write(&options.data_dir)?;
+    }
+
+    manifest.write(&options.data_dir)?;
+    Ok(manifest)
+}
+
+// Applies a delta to a cooked data directory in place, recomputing only the
+// neutron neighbor lists near changed neutron stars. Returns the new manifest,
+// what changed and how many neighbor lists were recomputed.
+pub fn update_data_dir(data_dir: &str, delta: DatasetDelta) -> std::io::Result<(Manifest, DeltaSummary, usize)> {
+    let (mut manifest, _, old_neutron_map) = open_data_dir(data_dir)?;
+    let data_dir = Path::new(data_dir);
+    let systems_file = data_dir.join(SYSTEMS_BINCODE_FILE);
+    let old_systems = read_star_systems_bincode(systems_file.to_str().unwrap(), |_| true);
+    let old_neutrons: Vec<_> = (0..old_neutron_map.len()).map(|i| old_neutron_map.get(i)).collect();
+    drop(old_neutron_map);
+
+    let (new_systems, summary) = apply_delta(old_systems, delta);
+    let (new_neutrons, recomputed) = update_neutron_star_systems(&old_neutrons, &new_systems, &summary.changed_neutron_positions, manifest.max_jump_distance, &manifest.pruning_policy()?);
+    manifest.system_count = new_systems.len() as u64;
+    manifest.neutron_count = new_neutrons.len() as u64;
+    manifest.dataset_hash = dataset_hash(new_systems.iter().map(|s| (s.id64, s.coords)));
+
+    for file in &mut manifest.files {
+        let path = data_dir.join(&file.path);
+        let tmp_path = format!("{}.tmp", path.display());
+        match file.stage.as_str() {
+            "import" => {
+                let records: Vec<StarSystemRecord> = new_systems.iter().cloned().map(|s| s.into()).collect();
+                write_star_systems_bincode(&records, &tmp_path)?;
+            }
+            "columnar" => write_columnar_systems(&new_systems, &tmp_path)?,
+            "neutrons" => write_neutron_file(&new_neutrons, &tmp_path)?,
+            "sqlite" => import_star_systems_sqlite(&new_systems, &tmp_path).map_err(std::io::Error::other)?,
+            _ => continue,
+        }
+        std::fs::rename(&tmp_path, &path)?;
+        file.size = std::fs::metadata(&path)?.len();
+        file.dataset_hash = manifest.dataset_hash;
+    }
+    manifest.write(data_dir.to_str().unwrap())?;
+    Ok((manifest, summary, recomputed))
+}
@@ -0,0 +1,232 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Told about long-running work: searches and cooking. One task runs at a
+// time; `advance` and `note` refer to the one begun last.
+pub trait Progress: Send + Sync {
+    // `total` is how many steps the task takes, if that's known up front
+    fn begin(&self, task: &str, total: Option<u64>);
+    // `done` steps of the task are finished so far
+    fn advance(&self, done: u64);
+    fn note(&self, message: &str);
+    fn end(&self);
+}
+
+impl fmt::Debug for dyn Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Progress")
+    }
+}
+
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+pub struct SilentProgress;
+
+impl Progress for SilentProgress {
+    fn begin(&self, _task: &str, _total: Option<u64>) {}
+    fn advance(&self, _done: u64) {}
+    fn note(&self, _message: &str) {}
+    fn end(&self) {}
+}
+
+// The task being reported on, and when it was last shown
+struct Task {
+    name: String,
+    total: Option<u64>,
+    done: u64,
+    started: Instant,
+    shown: Instant,
+}
+
+impl Task {
+    fn new(name: &str, total: Option<u64>) -> Task {
+        let now = Instant::now();
+        Task { name: name.to_string(), total, done: 0, started: now, shown: now }
+    }
+
+    // Time left at the average rate so far
+    fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        (self.done > 0 && self.done < total).then(|| self.started.elapsed().mul_f64((total - self.done) as f64 / self.done as f64))
+    }
+
+    // Whether `interval` has passed since the task was last shown, resetting the clock if so
+    fn due(&mut self, interval: Duration) -> bool {
+        let due = self.shown.elapsed() >= interval;
+        if due {
+            self.shown = Instant::now();
+        }
+        due
+    }
+}
+
+const BAR_WIDTH: usize = 30;
+const BAR_REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+// A bar redrawn in place on stderr
+#[derive(Default)]
+pub struct TerminalProgress {
+    task: Mutex<Option<Task>>,
+}
+
+impl TerminalProgress {
+    pub fn new() -> TerminalProgress {
+        TerminalProgress::default()
+    }
+
+    fn draw(task: &Task) {
+        let line = match task.total {
+            Some(total) if total > 0 => {
+                let fraction = (task.done as f64 / total as f64).min(1.0);
+                let filled = (fraction * BAR_WIDTH as f64) as usize;
+                let eta = task.eta().map(|eta| format!(" ETA {}", format_duration(eta))).unwrap_or_default();
+                format!("{} [{}{}] {:.1}% {}/{}{}", task.name, "#".repeat(filled), " ".repeat(BAR_WIDTH - filled), fraction * 100.0, task.done, total, eta)
+            }
+            _ => format!("{} {} done, {} elapsed", task.name, task.done, format_duration(task.started.elapsed())),
+        };
+        eprint!("\r\x1b[K{}", line);
+    }
+}
+
+impl Progress for TerminalProgress {
+    fn begin(&self, task: &str, total: Option<u64>) {
+        let task = Task::new(task, total);
+        TerminalProgress::draw(&task);
+        *self.task.lock().unwrap() = Some(task);
+    }
+
+    fn advance(&self, done: u64) {
+        if let Some(task) = self.task.lock().unwrap().as_mut() {
+            task.done = done;
+            if task.due(BAR_REDRAW_INTERVAL) {
+                TerminalProgress::draw(task);
+            }
+        }
+    }
+
+    fn note(&self, message: &str) {
+        eprintln!("\r\x1b[K{}", message);
+        if let Some(task) = self.task.lock().unwrap().as_ref() {
+            TerminalProgress::draw(task);
+        }
+    }
+
+    fn end(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            TerminalProgress::draw(&task);
+            eprintln!(" ({})", format_duration(task.started.elapsed()));
+        }
+    }
+}
+
+const LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+// One key=value line per event, at most one `advance` line a second
+pub struct LogProgress {
+    task: Mutex<Option<Task>>,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl LogProgress {
+    // Logs to stderr
+    pub fn new() -> LogProgress {
+        LogProgress::to_writer(Box::new(std::io::stderr()))
+    }
+
+    pub fn to_writer(out: Box<dyn Write + Send>) -> LogProgress {
+        LogProgress { task: Mutex::new(None), out: Mutex::new(out) }
+    }
+
+    // Notes outside a task are logged without one
+    fn log(&self, task: Option<&Task>, event: &str, fields: &str) {
+        let mut line = format!("progress event={}", event);
+        if let Some(task) = task {
+            line += &format!(" task={:?} done={} elapsed_ms={}", task.name, task.done, task.started.elapsed().as_millis());
+            if let Some(total) = task.total {
+                line += &format!(" total={}", total);
+            }
+            if let Some(eta) = task.eta() {
+                line += &format!(" eta_ms={}", eta.as_millis());
+            }
+        }
+        line += fields;
+        // Progress is best effort: a closed log mustn't stop the work
+        let _ = writeln!(self.out.lock().unwrap(), "{}", line);
+    }
+}
+
+impl Default for LogProgress {
+    fn default() -> LogProgress {
+        LogProgress::new()
+    }
+}
+
+impl Progress for LogProgress {
+    fn begin(&self, task: &str, total: Option<u64>) {
+        let task = Task::new(task, total);
+        self.log(Some(&task), "begin", "");
+        *self.task.lock().unwrap() = Some(task);
+    }
+
+    fn advance(&self, done: u64) {
+        if let Some(task) = self.task.lock().unwrap().as_mut() {
+            task.done = done;
+            if task.due(LOG_INTERVAL) {
+                self.log(Some(task), "advance", "");
+            }
+        }
+    }
+
+    fn note(&self, message: &str) {
+        let task = self.task.lock().unwrap();
+        self.log(task.as_ref(), "note", &format!(" message={:?}", message));
+    }
+
+    fn end(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            self.log(Some(&task), "end", "");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStyle {
+    Bar,
+    Log,
+    Silent,
+}
+
+impl ProgressStyle {
+    pub fn reporter(self) -> Arc<dyn Progress> {
+        match self {
+            ProgressStyle::Bar => Arc::new(TerminalProgress::new()),
+            ProgressStyle::Log => Arc::new(LogProgress::new()),
+            ProgressStyle::Silent => Arc::new(SilentProgress),
+        }
+    }
+}
+
+impl FromStr for ProgressStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bar" => Ok(ProgressStyle::Bar),
+            "log" => Ok(ProgressStyle::Log),
+            "none" => Ok(ProgressStyle::Silent),
+            _ => Err(format!("unknown progress style {:?} (expected bar, log or none)", s)),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+use crate::{distance_v, neutron_a_star, write_neutron_file, Float, NeutronFileMap, NeutronGrid, NeutronStarSystem, Systems, V3, NEUTRON_MAX_DISTANCE};
+
+// How a neutron star's candidate neighbors (every neutron star closer than
+// `max_distance`) are cut down to the edges stored in the graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PruningRule {
+    // Everything within jump range, plus farther stars that no kept neighbor
+    // is closer to. The original rule.
+    Occlusion,
+    // The k nearest in each of the eight coordinate octants
+    KNearestPerOctant(usize),
+    // The nearest in each of n cones around the star
+    Yao(usize),
+    // In each of n cones, the star whose projection onto the cone's axis is shortest
+    Theta(usize),
+    // Stars with no third star closer to both ends of the edge than they are
+    // to each other
+    RelativeNeighborhood,
+}
+
+impl fmt::Display for PruningRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PruningRule::Occlusion => write!(f, "occlusion"),
+            PruningRule::KNearestPerOctant(k) => write!(f, "octant:{}", k),
+            PruningRule::Yao(cones) => write!(f, "yao:{}", cones),
+            PruningRule::Theta(cones) => write!(f, "theta:{}", cones),
+            PruningRule::RelativeNeighborhood => write!(f, "rng"),
+        }
+    }
+}
+
+impl FromStr for PruningRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg.parse::<usize>().map_err(|e| format!("bad count in {:?}: {}", s, e))?)),
+            None => (s, None),
+        };
+        match (name, arg) {
+            ("occlusion", None) => Ok(PruningRule::Occlusion),
+            ("octant", Some(k)) if k > 0 => Ok(PruningRule::KNearestPerOctant(k)),
+            ("yao", Some(cones)) if cones > 0 => Ok(PruningRule::Yao(cones)),
+            ("theta", Some(cones)) if cones > 0 => Ok(PruningRule::Theta(cones)),
+            ("rng", None) => Ok(PruningRule::RelativeNeighborhood),
+            _ => Err(format!("unknown pruning rule {:?} (expected occlusion, octant:K, yao:N, theta:N or rng)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PruningPolicy {
+    pub rule: PruningRule,
+    // Stars at least this far away are never neighbors
+    pub max_distance: f32,
+}
+
+impl Default for PruningPolicy {
+    fn default() -> Self {
+        Self {
+            rule: PruningRule::Occlusion,
+            max_distance: NEUTRON_MAX_DISTANCE,
+        }
+    }
+}
+
+impl fmt::Display for PruningPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} max_distance={}", self.rule, self.max_distance)
+    }
+}
+
+// Cone axes spread evenly over the sphere (Fibonacci lattice)
+fn cone_axes(cones: usize) -> Vec<[f32; 3]> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    (0..cones).map(|i| {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / cones as f32;
+        let r = (1.0 - y * y).sqrt();
+        let phi = i as f32 * golden_angle;
+        [r * phi.cos(), y, r * phi.sin()]
+    }).collect()
+}
+
+fn offset(from: &V3, to: &V3) -> [f32; 3] {
+    [f32::from(to.0 - from.0), f32::from(to.1 - from.1), f32::from(to.2 - from.2)]
+}
+
+fn dot(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// The rule's per-build state, so cone axes are only worked out once
+pub(crate) struct Pruner {
+    rule: PruningRule,
+    axes: Vec<[f32; 3]>,
+}
+
+impl Pruner {
+    pub(crate) fn new(rule: PruningRule) -> Self {
+        let axes = match rule {
+            PruningRule::Yao(cones) | PruningRule::Theta(cones) => cone_axes(cones),
+            _ => Vec::new(),
+        };
+        Self { rule, axes }
+    }
+
+    fn cone(&self, v: &[f32; 3]) -> usize {
+        (0..self.axes.len()).max_by(|&a, &b| dot(v, &self.axes[a]).total_cmp(&dot(v, &self.axes[b]))).unwrap()
+    }
+
+    // `sorted` holds every candidate as (distance, neutron list index), nearest
+    // first with ties in neutron list order. Kept neighbors stay in that order.
+    pub(crate) fn prune(&self, grid: &NeutronGrid, n_idx_idx: u32, sorted: &[(Float, u32)], max_jump_distance: Float) -> Vec<u32> {
+        let start = grid.coords(n_idx_idx);
+        match self.rule {
+            PruningRule::Occlusion => {
+                let mut neighbors: Vec<u32> = Vec::new();
+                // Neighboring candidates tend to be blocked by the same star,
+                // so it's tried first
+                let mut last_blocker: Option<u32> = None;
+                for &(d, other) in sorted {
+                    if d <= max_jump_distance {
+                        neighbors.push(other);
+                        continue;
+                    }
+                    let neigh = grid.coords(other);
+                    let blocks = |existing: u32| d > distance_v(neigh, grid.coords(existing));
+                    if last_blocker.is_some_and(blocks) {
+                        continue;
+                    }
+                    match neighbors.iter().copied().find(|&existing| blocks(existing)) {
+                        Some(blocker) => last_blocker = Some(blocker),
+                        None => neighbors.push(other),
+                    }
+                }
+                neighbors
+            }
+            PruningRule::KNearestPerOctant(k) => {
+                let mut counts = [0; 8];
+                sorted.iter().filter(|&&(_, other)| {
+                    let v = offset(start, grid.coords(other));
+                    let octant = (v[0] < 0.0) as usize | ((v[1] < 0.0) as usize) << 1 | ((v[2] < 0.0) as usize) << 2;
+                    counts[octant] += 1;
+                    counts[octant] <= k
+                }).map(|&(_, other)| other).collect()
+            }
+            PruningRule::Yao(_) => {
+                let mut taken = vec![false; self.axes.len()];
+                sorted.iter().filter(|&&(_, other)| {
+                    let cone = self.cone(&offset(start, grid.coords(other)));
+                    !std::mem::replace(&mut taken[cone], true)
+                }).map(|&(_, other)| other).collect()
+            }
+            PruningRule::Theta(_) => {
+                let mut best: Vec<Option<(f32, usize)>> = vec![None; self.axes.len()];
+                for (i, &(_, other)) in sorted.iter().enumerate() {
+                    let v = offset(start, grid.coords(other));
+                    let cone = self.cone(&v);
+                    let projection = dot(&v, &self.axes[cone]);
+                    if best[cone].is_none_or(|(p, _)| projection < p) {
+                        best[cone] = Some((projection, i));
+                    }
+                }
+                let mut keep: Vec<usize> = best.into_iter().flatten().map(|(_, i)| i).collect();
+                keep.sort();
+                keep.into_iter().map(|i| sorted[i].1).collect()
+            }
+            PruningRule::RelativeNeighborhood => {
+                // Only stars nearer to the start than the candidate can be
+                // closer to both ends, and those come earlier in `sorted`
+                sorted.iter().enumerate().filter(|&(i, &(d, other))| {
+                    let neigh = grid.coords(other);
+                    !sorted[..i].iter().any(|&(d_w, w)| d_w < d && distance_v(neigh, grid.coords(w)) < d)
+                }).map(|(_, &(_, other))| other).collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PruningReportRow {
+    pub policy: PruningPolicy,
+    pub edges: usize,
+    pub max_degree: usize,
+    pub file_size: u64,
+    pub build_secs: f64,
+    pub search_secs: f64,
+    pub routes_found: usize,
+    // Summed over routes found by both this policy and the baseline
+    pub jumps: i64,
+    pub baseline_jumps: i64,
+    pub worst_extra_jumps: i64,
+}
+
+impl PruningReportRow {
+    pub fn average_degree(&self, neutron_count: usize) -> f64 {
+        self.edges as f64 / neutron_count.max(1) as f64
+    }
+
+    pub fn extra_jumps_percent(&self) -> f64 {
+        (self.jumps - self.baseline_jumps) as f64 * 100.0 / self.baseline_jumps.max(1) as f64
+    }
+}
+
+// Builds the graph under each policy and routes every pair with it, comparing
+// edge counts and route lengths against the first policy. Graph files are
+// written to `scratch_dir`.
+pub fn pruning_report<S: Systems>(systems: &S, policies: &[PruningPolicy], pairs: &[(u32, u32)], max_jump_distance: f32, jump_distance: Float, scratch_dir: &str) -> std::io::Result<Vec<PruningReportRow>> {
+    std::fs::create_dir_all(scratch_dir)?;
+    let grid = NeutronGrid::new(systems);
+    let mut baseline: Option<Vec<Option<i64>>> = None;
+    let mut rows = Vec::new();
+    for (i, policy) in policies.iter().enumerate() {
+        let build_start = Instant::now();
+        let mut neutrons: Vec<NeutronStarSystem> = Vec::with_capacity(grid.len());
+        grid.build_chunks_with_policy(max_jump_distance, policy, |_| false, |_, chunk| -> Result<(), ()> {
+            neutrons.extend(chunk);
+            Ok(())
+        }).unwrap();
+        let build_secs = build_start.elapsed().as_secs_f64();
+        let edges = neutrons.iter().map(|n| n.neighbors.len()).sum();
+        let max_degree = neutrons.iter().map(|n| n.neighbors.len()).max().unwrap_or(0);
+        let path = format!("{}/neutrons-{}.nbr", scratch_dir, i);
+        write_neutron_file(&neutrons, &path)?;
+        drop(neutrons);
+        let neutron_map = NeutronFileMap::new(&path);
+
+        let search_start = Instant::now();
+        let route_jumps: Vec<Option<i64>> = pairs.iter().map(|&(start, goal)| {
+            neutron_a_star(systems, &neutron_map, start, goal, jump_distance).map(|path| path.len() as i64)
+        }).collect();
+        let search_secs = search_start.elapsed().as_secs_f64();
+        let baseline = baseline.get_or_insert_with(|| route_jumps.clone());
+        let mut row = PruningReportRow {
+            policy: *policy,
+            edges,
+            max_degree,
+            file_size: std::fs::metadata(&path)?.len(),
+            build_secs,
+            search_secs,
+            routes_found: route_jumps.iter().flatten().count(),
+            jumps: 0,
+            baseline_jumps: 0,
+            worst_extra_jumps: 0,
+        };
+        for (jumps, baseline_jumps) in route_jumps.iter().zip(baseline.iter()) {
+            if let (Some(jumps), Some(baseline_jumps)) = (jumps, baseline_jumps) {
+                row.jumps += jumps;
+                row.baseline_jumps += baseline_jumps;
+                row.worst_extra_jumps = row.worst_extra_jumps.max(jumps - baseline_jumps);
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
@@ -0,0 +1,59 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::{system_distance, Float, Systems};
+
+// Dijkstra over the full jump graph, trying every system as the next hop. Each
+// jump costs 1 and may be up to `jump_distance`, or 4 * `jump_distance` from a
+// neutron star. Quadratic, so only for small galaxies: it's the yardstick the
+// real searches are tested against. Like `a_star`, the path excludes the start.
+pub fn reference_route<S: Systems + ?Sized>(systems: &S, start_idx: u32, goal_idx: u32, jump_distance: Float) -> Option<Vec<u32>> {
+    let count = systems.len() as usize;
+    let mut jumps: Vec<Option<u32>> = vec![None; count];
+    let mut parent: Vec<u32> = vec![u32::MAX; count];
+    let mut done = vec![false; count];
+    let mut to_visit = BinaryHeap::new();
+    jumps[start_idx as usize] = Some(0);
+    to_visit.push(Reverse((0u32, start_idx)));
+    while let Some(Reverse((current_jumps, current_idx))) = to_visit.pop() {
+        if done[current_idx as usize] {
+            continue;
+        }
+        done[current_idx as usize] = true;
+        if current_idx == goal_idx {
+            break;
+        }
+        let range = if systems.is_neutron(current_idx) { jump_distance * Float::from(4.0) } else { jump_distance };
+        for next_idx in 0..systems.len() {
+            if done[next_idx as usize] || system_distance(systems, current_idx, next_idx) > range {
+                continue;
+            }
+            if jumps[next_idx as usize].is_none_or(|j| current_jumps + 1 < j) {
+                jumps[next_idx as usize] = Some(current_jumps + 1);
+                parent[next_idx as usize] = current_idx;
+                to_visit.push(Reverse((current_jumps + 1, next_idx)));
+            }
+        }
+    }
+    if !done[goal_idx as usize] || start_idx == goal_idx {
+        return None;
+    }
+    let mut path = vec![goal_idx];
+    while parent[*path.last().unwrap() as usize] != start_idx {
+        path.push(parent[*path.last().unwrap() as usize]);
+    }
+    path.reverse();
+    Some(path)
+}
+
+// Whether every hop of `path` (which excludes `start_idx`) is a legal jump
+pub fn is_valid_route<S: Systems + ?Sized>(systems: &S, start_idx: u32, path: &[u32], jump_distance: Float) -> bool {
+    let mut from_idx = start_idx;
+    for &to_idx in path {
+        let range = if systems.is_neutron(from_idx) { jump_distance * Float::from(4.0) } else { jump_distance };
+        if system_distance(systems, from_idx, to_idx) > range {
+            return false;
+        }
+        from_idx = to_idx;
+    }
+    true
+}
@@ -0,0 +1,84 @@
+use sqlite::{Connection, State};
+use crate::{Float, StarSystem};
+
+// Systems are stored in routing order: `id` is the index into the sorted
+// system list, so neutron files cooked from the same data line up with it.
+// `id64` is the stable system address, stored as its i64 bit pattern.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS systems (
+        id INTEGER PRIMARY KEY,
+        id64 INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        x REAL NOT NULL,
+        y REAL NOT NULL,
+        z REAL NOT NULL,
+        distance_from_sol REAL NOT NULL,
+        main_star_type TEXT NOT NULL,
+        is_neutron INTEGER NOT NULL
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS systems_id64 ON systems(id64);
+    CREATE INDEX IF NOT EXISTS systems_name ON systems(name);
+    CREATE INDEX IF NOT EXISTS systems_main_star_type ON systems(main_star_type);
+    CREATE INDEX IF NOT EXISTS systems_is_neutron ON systems(is_neutron) WHERE is_neutron;
+    CREATE VIRTUAL TABLE IF NOT EXISTS systems_rtree USING rtree(id, min_x, max_x, min_y, max_y, min_z, max_z);
+";
+
+pub fn open_star_systems_sqlite(db_path: &str) -> sqlite::Result<Connection> {
+    let connection = sqlite::open(db_path)?;
+    connection.execute(SCHEMA)?;
+    Ok(connection)
+}
+
+pub fn import_star_systems_sqlite(systems: &[StarSystem], db_path: &str) -> sqlite::Result<()> {
+    let connection = open_star_systems_sqlite(db_path)?;
+    connection.execute("BEGIN; DELETE FROM systems; DELETE FROM systems_rtree;")?;
+    {
+        let mut insert_system = connection.prepare("INSERT INTO systems (id, id64, name, x, y, z, distance_from_sol, main_star_type, is_neutron) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+        let mut insert_rtree = connection.prepare("INSERT INTO systems_rtree (id, min_x, max_x, min_y, max_y, min_z, max_z) VALUES (?, ?, ?, ?, ?, ?, ?)")?;
+        for (id, system) in systems.iter().enumerate() {
+            let id = id as i64;
+            let (x, y, z) = system.coords;
+            let (x, y, z) = (f32::from(x) as f64, f32::from(y) as f64, f32::from(z) as f64);
+            insert_system.reset()?;
+            insert_system.bind((1, id))?;
+            insert_system.bind((2, system.id64 as i64))?;
+            insert_system.bind((3, system.name.as_str()))?;
+            insert_system.bind((4, x))?;
+            insert_system.bind((5, y))?;
+            insert_system.bind((6, z))?;
+            insert_system.bind((7, f32::from(system.distance_from_sol) as f64))?;
+            insert_system.bind((8, system.main_star_type.as_str()))?;
+            insert_system.bind((9, system.is_neutron as i64))?;
+            while insert_system.next()? != State::Done {}
+
+            insert_rtree.reset()?;
+            insert_rtree.bind((1, id))?;
+            for (i, v) in [x, x, y, y, z, z].into_iter().enumerate() {
+                insert_rtree.bind((i + 2, v))?;
+            }
+            while insert_rtree.next()? != State::Done {}
+        }
+    }
+    connection.execute("COMMIT;")
+}
+
+pub fn read_star_systems_sqlite(db_path: &str, filter: fn(&StarSystem) -> bool) -> sqlite::Result<Vec<StarSystem>> {
+    let connection = sqlite::open(db_path)?;
+    let mut statement = connection.prepare("SELECT name, x, y, z, distance_from_sol, main_star_type, is_neutron, id64 FROM systems ORDER BY id")?;
+    let mut retval = Vec::new();
+    while statement.next()? == State::Row {
+        let coord = |i: usize| -> sqlite::Result<Float> { Ok((statement.read::<f64, _>(i)? as f32).into()) };
+        let system = StarSystem {
+            name: statement.read::<String, _>(0)?,
+            coords: (coord(1)?, coord(2)?, coord(3)?),
+            distance_from_sol: coord(4)?,
+            main_star_type: statement.read::<String, _>(5)?,
+            is_neutron: statement.read::<i64, _>(6)? != 0,
+            id64: statement.read::<i64, _>(7)? as u64,
+        };
+        if filter(&system) {
+            retval.push(system);
+        }
+    }
+    Ok(retval)
+}
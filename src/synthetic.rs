@@ -0,0 +1,233 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::{name_id64, sort_star_system_records, StarSystemRecord};
+
+const NEUTRON_STAR_TYPE: &str = "Neutron Star";
+// The game places systems on a 1/32 ly grid
+const COORD_STEP: f64 = 1.0 / 32.0;
+
+// Where a share of the systems are placed, relative to the galaxy's center
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DensityProfile {
+    // Exponential falloff from the center within a thin plane
+    Disk,
+    // Dense gaussian bulge around the center
+    Core,
+    // Uniform over the whole galaxy sphere, so very sparse
+    Halo,
+    // Tight gaussian clumps scattered over the disk
+    Clusters,
+}
+
+impl fmt::Display for DensityProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DensityProfile::Disk => write!(f, "disk"),
+            DensityProfile::Core => write!(f, "core"),
+            DensityProfile::Halo => write!(f, "halo"),
+            DensityProfile::Clusters => write!(f, "clusters"),
+        }
+    }
+}
+
+impl FromStr for DensityProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disk" => Ok(DensityProfile::Disk),
+            "core" => Ok(DensityProfile::Core),
+            "halo" => Ok(DensityProfile::Halo),
+            "clusters" => Ok(DensityProfile::Clusters),
+            _ => Err(format!("unknown density profile {:?} (expected disk, core, halo or clusters)", s)),
+        }
+    }
+}
+
+// Star classes other than neutron stars, weighted roughly like the real dump
+const DEFAULT_STAR_CLASSES: &[(&str, f64)] = &[
+    ("M (Red dwarf) Star", 0.50),
+    ("K (Yellow-Orange) Star", 0.15),
+    ("L (Brown dwarf) Star", 0.08),
+    ("T (Brown dwarf) Star", 0.06),
+    ("Y (Brown dwarf) Star", 0.03),
+    ("G (White-Yellow) Star", 0.06),
+    ("F (White) Star", 0.04),
+    ("A (Blue-White) Star", 0.02),
+    ("B (Blue-White) Star", 0.01),
+    ("T Tauri Star", 0.02),
+    ("White Dwarf (DA) Star", 0.02),
+    ("Black Hole", 0.01),
+];
+
+// A seeded recipe for a fake galaxy. The same settings always give the same
+// systems, so a generated dataset can stand in for the real dump in tests,
+// benchmarks and bug reports.
+#[derive(Debug, Clone)]
+pub struct SyntheticGalaxy {
+    pub seed: u64,
+    pub system_count: usize,
+    pub center: (f32, f32, f32),
+    // No system is farther than this from the center
+    pub radius: f32,
+    // Relative weight of each profile; zero-weight profiles are unused
+    pub profiles: Vec<(DensityProfile, f64)>,
+    pub disk_scale_length: f32,
+    pub disk_scale_height: f32,
+    pub core_radius: f32,
+    pub cluster_count: usize,
+    pub cluster_radius: f32,
+    // Fraction of systems whose main star is a neutron star
+    pub neutron_ratio: f64,
+    // Relative weight of each main star type for the other systems
+    pub star_classes: Vec<(String, f64)>,
+}
+
+impl Default for SyntheticGalaxy {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            system_count: 100_000,
+            center: (0.0, 0.0, 0.0),
+            radius: 10_000.0,
+            profiles: vec![
+                (DensityProfile::Disk, 0.75),
+                (DensityProfile::Core, 0.15),
+                (DensityProfile::Halo, 0.02),
+                (DensityProfile::Clusters, 0.08),
+            ],
+            disk_scale_length: 3000.0,
+            disk_scale_height: 300.0,
+            core_radius: 1500.0,
+            cluster_count: 50,
+            cluster_radius: 100.0,
+            neutron_ratio: 0.01,
+            star_classes: DEFAULT_STAR_CLASSES.iter().map(|&(name, weight)| (name.to_string(), weight)).collect(),
+        }
+    }
+}
+
+// SplitMix64. Kept here rather than pulled from a crate so a seed means the
+// same galaxy whatever dependency versions are in use.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn normal(&mut self) -> f64 {
+        let (u, v) = (1.0 - self.unit(), self.unit());
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+
+    fn exponential(&mut self, scale: f64) -> f64 {
+        -scale * (1.0 - self.unit()).ln()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.unit() * n as f64) as usize
+    }
+
+    fn weighted<'a, T>(&mut self, choices: &'a [(T, f64)]) -> &'a T {
+        let total: f64 = choices.iter().map(|(_, weight)| weight).sum();
+        let mut pick = self.unit() * total;
+        for (choice, weight) in choices {
+            if pick < *weight {
+                return choice;
+            }
+            pick -= weight;
+        }
+        &choices.iter().rev().find(|(_, weight)| *weight > 0.0).unwrap().0
+    }
+}
+
+impl SyntheticGalaxy {
+    fn disk_point(&self, rng: &mut Rng) -> [f64; 3] {
+        let r = rng.exponential(self.disk_scale_length as f64);
+        let angle = rng.unit() * std::f64::consts::TAU;
+        let height = rng.exponential(self.disk_scale_height as f64) * if rng.unit() < 0.5 { -1.0 } else { 1.0 };
+        [r * angle.cos(), height, r * angle.sin()]
+    }
+
+    fn gaussian_point(rng: &mut Rng, around: [f64; 3], sigma: f64) -> [f64; 3] {
+        [around[0] + rng.normal() * sigma, around[1] + rng.normal() * sigma, around[2] + rng.normal() * sigma]
+    }
+
+    fn halo_point(&self, rng: &mut Rng) -> [f64; 3] {
+        let radius = self.radius as f64;
+        loop {
+            let p = [(rng.unit() * 2.0 - 1.0) * radius, (rng.unit() * 2.0 - 1.0) * radius, (rng.unit() * 2.0 - 1.0) * radius];
+            if p.iter().map(|c| c * c).sum::<f64>() <= radius * radius {
+                return p;
+            }
+        }
+    }
+
+    // Offset from the center, redrawn until it falls inside the galaxy
+    fn point(&self, rng: &mut Rng, profile: DensityProfile, clusters: &[[f64; 3]]) -> ([f64; 3], Option<usize>) {
+        let radius = self.radius as f64;
+        loop {
+            let (p, cluster) = match profile {
+                DensityProfile::Disk => (self.disk_point(rng), None),
+                // Nearly everything within core_radius
+                DensityProfile::Core => (Self::gaussian_point(rng, [0.0; 3], self.core_radius as f64 / 3.0), None),
+                DensityProfile::Halo => (self.halo_point(rng), None),
+                DensityProfile::Clusters => {
+                    let cluster = rng.below(clusters.len());
+                    (Self::gaussian_point(rng, clusters[cluster], self.cluster_radius as f64 / 2.0), Some(cluster))
+                }
+            };
+            if p.iter().map(|c| c * c).sum::<f64>() <= radius * radius {
+                return (p, cluster);
+            }
+        }
+    }
+
+    // Sorted by distance from Sol like every other system list. Names are
+    // unique, so id64s are too.
+    pub fn generate(&self) -> Vec<StarSystemRecord> {
+        let mut rng = Rng(self.seed);
+        let profiles: Vec<(DensityProfile, f64)> = self.profiles.iter().copied().filter(|&(_, weight)| weight > 0.0).collect();
+        assert!(!profiles.is_empty(), "SyntheticGalaxy needs a density profile with positive weight");
+        assert!(self.star_classes.iter().any(|&(_, weight)| weight > 0.0) || self.neutron_ratio >= 1.0, "SyntheticGalaxy needs a star class with positive weight");
+        let clusters: Vec<[f64; 3]> = (0..self.cluster_count.max(1)).map(|_| self.point(&mut rng, DensityProfile::Disk, &[]).0).collect();
+        let (cx, cy, cz) = self.center;
+        let snap = |c: f64| ((c / COORD_STEP).round() * COORD_STEP) as f32;
+
+        let mut records: Vec<StarSystemRecord> = (0..self.system_count).map(|i| {
+            let profile = *rng.weighted(&profiles);
+            let (p, cluster) = self.point(&mut rng, profile, &clusters);
+            let (x, y, z) = (snap(cx as f64 + p[0]), snap(cy as f64 + p[1]), snap(cz as f64 + p[2]));
+            let main_star_type = if rng.unit() < self.neutron_ratio {
+                NEUTRON_STAR_TYPE.to_string()
+            } else {
+                rng.weighted(&self.star_classes).clone()
+            };
+            let name = match cluster {
+                Some(cluster) => format!("Synth Cluster {} {}", cluster, i),
+                None => format!("Synth {} {}", profile, i),
+            };
+            StarSystemRecord{
+                id64: Some(name_id64(&name)),
+                name,
+                Coord_X: x,
+                Coord_Y: y,
+                Coord_Z: z,
+                mainStarType: main_star_type,
+                d_from_sol: (x * x + y * y + z * z).sqrt(),
+            }
+        }).collect();
+        sort_star_system_records(&mut records);
+        records
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use rayon::prelude::*;
+use crate::{distance_v, Float, NeutronGrid, NeutronStarSystem, PruningPolicy, StarSystem, StarSystemRecord, V3};
+
+#[derive(Debug, Default, bincode::Encode, bincode::Decode)]
+pub struct DatasetDelta {
+    // New systems, or replacements for existing systems with the same
+    // `system_id64()`. Records without an id64 of their own fall back to a hash
+    // of name and position, so to replace one after it moved, pin the old
+    // record's id64 (as `DatasetDiff::to_delta` does).
+    pub upserts: Vec<StarSystemRecord>,
+    // id64s of systems to drop
+    pub removals: Vec<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct DeltaSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    // Old and new positions of every neutron star that appeared, disappeared
+    // or moved. Neutron neighbor lists near these need recomputing.
+    pub changed_neutron_positions: Vec<V3>,
+}
+
+pub fn write_delta(delta: &DatasetDelta, output_filepath: &str) -> std::io::Result<()> {
+    let out_f = File::create(output_filepath)?;
+    let mut compressed_out = flate2::write::GzEncoder::new(out_f, flate2::Compression::default());
+    bincode::encode_into_std_write(delta, &mut compressed_out, bincode::config::standard()).map_err(std::io::Error::other)?;
+    compressed_out.finish()?;
+    Ok(())
+}
+
+// `.delta` files hold a full DatasetDelta; any system dump is read as upserts only
+pub fn read_delta(filename: &str) -> DatasetDelta {
+    if filename.ends_with(".delta") {
+        let f = File::open(filename).unwrap();
+        let mut gz = flate2::read::GzDecoder::new(f);
+        bincode::decode_from_std_read(&mut gz, bincode::config::standard()).unwrap()
+    } else if filename.ends_with(".csv") {
+        DatasetDelta{upserts: crate::read_star_system_records_csv(filename), removals: Vec::new()}
+    } else if crate::is_json_lines_file(filename) {
+        DatasetDelta{upserts: crate::read_star_system_records_json(filename), removals: Vec::new()}
+    } else {
+        DatasetDelta{upserts: crate::read_star_system_records_bincode(filename), removals: Vec::new()}
+    }
+}
+
+// Patches a sorted system list in place of a full re-import. The result is
+// sorted by distance from Sol again.
+pub fn apply_delta(systems: Vec<StarSystem>, delta: DatasetDelta) -> (Vec<StarSystem>, DeltaSummary) {
+    let mut summary = DeltaSummary::default();
+    let mut upserts: HashMap<u64, StarSystem> = delta.upserts.into_iter().map(|r| {
+        let system: StarSystem = r.into();
+        (system.id64, system)
+    }).collect();
+    let removals: HashSet<u64> = delta.removals.into_iter().collect();
+    let mut retval = Vec::with_capacity(systems.len() + upserts.len());
+    for system in systems {
+        if removals.contains(&system.id64) {
+            summary.removed += 1;
+            if system.is_neutron {
+                summary.changed_neutron_positions.push(system.coords);
+            }
+            continue;
+        }
+        match upserts.remove(&system.id64) {
+            Some(new_system) => {
+                summary.updated += 1;
+                if (system.is_neutron || new_system.is_neutron) && (system.is_neutron != new_system.is_neutron || system.coords != new_system.coords) {
+                    if system.is_neutron {
+                        summary.changed_neutron_positions.push(system.coords);
+                    }
+                    if new_system.is_neutron {
+                        summary.changed_neutron_positions.push(new_system.coords);
+                    }
+                }
+                retval.push(new_system);
+            }
+            None => retval.push(system),
+        }
+    }
+    for (_, new_system) in upserts {
+        summary.added += 1;
+        if new_system.is_neutron {
+            summary.changed_neutron_positions.push(new_system.coords);
+        }
+        retval.push(new_system);
+    }
+    retval.sort();
+    (retval, summary)
+}
+
+// Rebuilds the neutron graph for `new_systems` reusing `old_neutrons` wherever
+// no changed neutron star is close enough to affect the neighbor list. Returns
+// the graph and how many lists were recomputed.
+pub fn update_neutron_star_systems(old_neutrons: &[NeutronStarSystem], new_systems: &[StarSystem], changed_neutron_positions: &[V3], max_jump_distance: f32, policy: &PruningPolicy) -> (Vec<NeutronStarSystem>, usize) {
+    let max_jump_distance = Float::from(max_jump_distance);
+    let max_distance = Float::from(policy.max_distance);
+    let grid = NeutronGrid::new(new_systems);
+    let neutron_stars: Vec<u32> = (0..grid.len() as u32).map(|n_idx_idx| grid.idx(n_idx_idx)).collect();
+    let new_n_idx_by_id64: HashMap<u64, u32> = (0..grid.len() as u32).map(|n_idx_idx| (grid.id64(n_idx_idx), n_idx_idx)).collect();
+    // old neutron index -> new neutron index, for stars present in both
+    let old_to_new: Vec<Option<u32>> = old_neutrons.iter().map(|n| new_n_idx_by_id64.get(&n.id64).copied()).collect();
+    let mut reusable: Vec<Option<&NeutronStarSystem>> = vec![None; neutron_stars.len()];
+    for (old_n, new_n) in old_neutrons.iter().zip(&old_to_new) {
+        if let Some(new_n) = new_n {
+            reusable[*new_n as usize] = Some(old_n);
+        }
+    }
+
+    let results: Vec<(NeutronStarSystem, bool)> = neutron_stars.par_iter().zip(reusable.par_iter()).enumerate().map(|(n_idx_idx, (&n_idx, reusable))| {
+        let coords = &new_systems[n_idx as usize].coords;
+        let affected = changed_neutron_positions.iter().any(|changed| distance_v(coords, changed) < max_distance);
+        if let (false, Some(old_n)) = (affected, reusable) {
+            let neighbors = old_n.neighbors.iter().map(|&old| old_to_new[old as usize].expect("unaffected neutron star lost a neighbor")).collect();
+            (NeutronStarSystem{idx: n_idx, id64: new_systems[n_idx as usize].id64, neighbors}, false)
+        } else {
+            (grid.neutron_star_system(n_idx_idx as u32, max_jump_distance, policy), true)
+        }
+    }).collect();
+    let recomputed = results.iter().filter(|(_, recomputed)| *recomputed).count();
+    (results.into_iter().map(|(n, _)| n).collect(), recomputed)
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::fmt;
+use rayon::prelude::*;
+use crate::{a_star, neighbors, system_distance, Float, Systems};
+
+pub const NEUTRON_BOOST: f32 = 4.0;
+pub const WHITE_DWARF_BOOST: f32 = 1.5;
+
+// How far a supercharged jump from a star of this type reaches, as a multiple
+// of the ship's range
+pub fn boost_factor(main_star_type: &str) -> f32 {
+    if main_star_type == "Neutron Star" {
+        NEUTRON_BOOST
+    } else if main_star_type.starts_with("White Dwarf") {
+        WHITE_DWARF_BOOST
+    } else {
+        1.0
+    }
+}
+
+// KGBFOAM main sequence stars and their giants
+pub fn is_scoopable(main_star_type: &str) -> bool {
+    ["O ", "B ", "A ", "F ", "G ", "K ", "M "].iter().any(|class| main_star_type.starts_with(class))
+}
+
+// Frame shift drive fuel use: a jump of d ly (d / boost when supercharged)
+// costs linear_constant / 1000 * (d * total mass / optimal_mass) ^ power_constant
+// tons, and never more than max_fuel_per_jump.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuelModel {
+    pub tank: f32,
+    pub max_fuel_per_jump: f32,
+    // Everything but the fuel: hull, modules and cargo
+    pub mass: f32,
+    pub optimal_mass: f32,
+    pub linear_constant: f32,
+    pub power_constant: f32,
+    // Refill to a full tank on arriving at a scoopable star
+    pub scoop: bool,
+}
+
+impl FuelModel {
+    pub fn fuel_cost(&self, distance: f32, boost: f32, fuel: f32) -> f32 {
+        self.linear_constant / 1000.0 * (distance / boost * (self.mass + fuel) / self.optimal_mass).powf(self.power_constant)
+    }
+
+    // Longest unboosted jump on max_fuel_per_jump with `fuel` in the tank
+    pub fn max_range(&self, fuel: f32) -> f32 {
+        self.optimal_mass / (self.mass + fuel) * (1000.0 * self.max_fuel_per_jump / self.linear_constant).powf(1.0 / self.power_constant)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShipParams {
+    // Unboosted range the route was planned with
+    pub jump_distance: Float,
+    // None skips the fuel checks
+    pub fuel: Option<FuelModel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HopProblem {
+    // The same system twice in a row
+    SameSystem,
+    // Beyond the unboosted range from a star that can't supercharge the drive
+    NoBoost { range: f32 },
+    // Beyond range even with the origin's boost
+    OutOfRange { range: f32, boost: f32 },
+    OverFuelLimit { needed: f32, limit: f32 },
+    OutOfFuel { needed: f32, available: f32 },
+}
+
+impl fmt::Display for HopProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HopProblem::SameSystem => write!(f, "jumps to the system it's already in"),
+            HopProblem::NoBoost { range } => write!(f, "beyond the {} ly range, and only neutron stars and white dwarfs can boost", range),
+            HopProblem::OutOfRange { range, boost } => write!(f, "beyond the {} ly range even with the x{} boost", range * boost, boost),
+            HopProblem::OverFuelLimit { needed, limit } => write!(f, "needs {:.2} t of fuel but the drive takes at most {} t per jump", needed, limit),
+            HopProblem::OutOfFuel { needed, available } => write!(f, "needs {:.2} t of fuel with only {:.2} t left", needed, available),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hop {
+    pub from: u32,
+    pub to: u32,
+    pub distance: f32,
+    // 1.0 for an unboosted jump
+    pub boost: f32,
+    // With a fuel model: fuel burned, fuel in the tank on arrival and whether
+    // it was then scooped full
+    pub fuel_used: Option<f32>,
+    pub fuel_left: Option<f32>,
+    pub refuelled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repair {
+    // Drop route[position]
+    Remove { position: usize },
+    // Put `systems` between route[position - 1] and route[position]
+    Insert { position: usize, systems: Vec<u32> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidHop {
+    // The hop from route[hop] to route[hop + 1]
+    pub hop: usize,
+    pub problem: HopProblem,
+    pub repair: Option<Repair>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteCheck {
+    // Every hop up to and including the first invalid one
+    pub hops: Vec<Hop>,
+    pub invalid: Option<InvalidHop>,
+}
+
+impl RouteCheck {
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_none()
+    }
+}
+
+// A scoopable star to refuel at between `from_idx` and `to_idx`, reachable on
+// the fuel left and leaving a full tank enough for the rest. Closest total
+// distance wins.
+fn refuel_stop<S: Systems>(systems: &S, from_idx: u32, to_idx: u32, jump_distance: Float, fuel: &FuelModel, fuel_left: f32) -> Option<u32> {
+    let range = |idx: u32| f32::from(jump_distance) * boost_factor(systems.main_star_type(idx));
+    let mut best: Option<(f32, u32)> = None;
+    // `neighbors` already boosts neutron stars
+    let search_range = if systems.is_neutron(from_idx) { jump_distance } else { Float::from(range(from_idx)) };
+    for stop_idx in neighbors(systems, from_idx, search_range) {
+        if !is_scoopable(systems.main_star_type(stop_idx)) {
+            continue;
+        }
+        let there = f32::from(system_distance(systems, from_idx, stop_idx));
+        let onward = f32::from(system_distance(systems, stop_idx, to_idx));
+        let there_boost = if there > f32::from(jump_distance) { boost_factor(systems.main_star_type(from_idx)) } else { 1.0 };
+        let onward_boost = if onward > f32::from(jump_distance) { boost_factor(systems.main_star_type(stop_idx)) } else { 1.0 };
+        let there_fuel = fuel.fuel_cost(there, there_boost, fuel_left);
+        let onward_fuel = fuel.fuel_cost(onward, onward_boost, fuel.tank);
+        let feasible = there <= range(from_idx) && onward <= range(stop_idx)
+            && there_fuel <= fuel_left.min(fuel.max_fuel_per_jump) && onward_fuel <= fuel.max_fuel_per_jump;
+        if feasible && best.is_none_or(|(d, _)| there + onward < d) {
+            best = Some((there + onward, stop_idx));
+        }
+    }
+    best.map(|(_, stop_idx)| stop_idx)
+}
+
+fn repair<S: Systems>(systems: &S, route: &[u32], hop: usize, problem: &HopProblem, ship: &ShipParams, fuel_left: Option<f32>) -> Option<Repair> {
+    let (from_idx, to_idx) = (route[hop], route[hop + 1]);
+    match problem {
+        HopProblem::SameSystem => Some(Repair::Remove { position: hop + 1 }),
+        HopProblem::OutOfFuel { .. } => {
+            let fuel = ship.fuel.as_ref().filter(|fuel| fuel.scoop)?;
+            let stop_idx = refuel_stop(systems, from_idx, to_idx, ship.jump_distance, fuel, fuel_left?)?;
+            Some(Repair::Insert { position: hop + 1, systems: vec![stop_idx] })
+        }
+        // Shorter jumps: fill the gap with a route of plain and neutron jumps,
+        // within what the drive can do on a full tank
+        HopProblem::NoBoost { .. } | HopProblem::OutOfRange { .. } | HopProblem::OverFuelLimit { .. } => {
+            let fuel_range = ship.fuel.map_or(f32::INFINITY, |fuel| fuel.max_range(fuel.tank));
+            let jump_distance = Float::from(f32::from(ship.jump_distance).min(fuel_range));
+            let mut path = a_star(systems, from_idx, to_idx, jump_distance, None)?;
+            path.pop();
+            (!path.is_empty()).then_some(Repair::Insert { position: hop + 1, systems: path })
+        }
+    }
+}
+
+// Flies `route` (a list of systems, start included) with `ship`, starting on
+// a full tank, and stops at the first hop that can't be made.
+pub fn validate_route<S: Systems>(systems: &S, route: &[u32], ship: &ShipParams) -> RouteCheck {
+    let jump_distance = f32::from(ship.jump_distance);
+    let mut fuel_left = ship.fuel.map(|fuel| fuel.tank);
+    let mut hops = Vec::new();
+    for (hop, pair) in route.windows(2).enumerate() {
+        let (from_idx, to_idx) = (pair[0], pair[1]);
+        let distance = f32::from(system_distance(systems, from_idx, to_idx));
+        let available_boost = boost_factor(systems.main_star_type(from_idx));
+        let boost = if distance > jump_distance { available_boost } else { 1.0 };
+        let mut problem = if from_idx == to_idx {
+            Some(HopProblem::SameSystem)
+        } else if distance > jump_distance && available_boost == 1.0 {
+            Some(HopProblem::NoBoost { range: jump_distance })
+        } else if distance > jump_distance * boost {
+            Some(HopProblem::OutOfRange { range: jump_distance, boost })
+        } else {
+            None
+        };
+        let mut fuel_used = None;
+        let mut refuelled = false;
+        if let (None, Some(fuel), Some(left)) = (&problem, &ship.fuel, fuel_left) {
+            let needed = fuel.fuel_cost(distance, boost, left);
+            if needed > fuel.max_fuel_per_jump {
+                problem = Some(HopProblem::OverFuelLimit { needed, limit: fuel.max_fuel_per_jump });
+            } else if needed > left {
+                problem = Some(HopProblem::OutOfFuel { needed, available: left });
+            } else {
+                fuel_used = Some(needed);
+                refuelled = fuel.scoop && is_scoopable(systems.main_star_type(to_idx));
+            }
+        }
+        if let Some(problem) = problem {
+            let repair = repair(systems, route, hop, &problem, ship, fuel_left);
+            hops.push(Hop { from: from_idx, to: to_idx, distance, boost, fuel_used: None, fuel_left, refuelled: false });
+            return RouteCheck { hops, invalid: Some(InvalidHop { hop, problem, repair }) };
+        }
+        if let (Some(fuel), Some(left), Some(used)) = (&ship.fuel, fuel_left.as_mut(), fuel_used) {
+            *left = if refuelled { fuel.tank } else { *left - used };
+        }
+        hops.push(Hop { from: from_idx, to: to_idx, distance, boost, fuel_used, fuel_left, refuelled });
+    }
+    RouteCheck { hops, invalid: None }
+}
+
+// Looks up every name in one pass over the systems. Names are matched
+// exactly; an ambiguous name resolves to the first of its systems in the
+// sorted order, the one closest to Sol.
+pub fn find_systems_by_name<S: Systems>(systems: &S, names: &[&str]) -> Vec<Option<u32>> {
+    let wanted: HashMap<&str, usize> = names.iter().enumerate().map(|(i, &name)| (name, i)).collect();
+    let found: Vec<(&str, u32)> = (0..systems.len()).into_par_iter()
+        .filter_map(|idx| wanted.get_key_value(systems.name(idx)).map(|(&name, _)| (name, idx)))
+        .collect();
+    let mut first: HashMap<&str, u32> = HashMap::new();
+    for (name, idx) in found {
+        first.entry(name).or_insert(idx);
+    }
+    let found = first;
+    names.iter().map(|name| found.get(name).copied()).collect()
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use neutron_route_finder::*;
+use common::neutron_file;
+
+const JUMP_RANGE: f32 = 20.0;
+
+struct Case {
+    systems: VecMap<StarSystem>,
+    neutrons: NeutronFileMap,
+    start: u32,
+    goal: u32,
+}
+
+// Far enough apart that the search takes a few thousand expansions
+fn case(name: &str) -> Case {
+    let galaxy = SyntheticGalaxy {
+        seed: 7,
+        system_count: 3000,
+        radius: 250.0,
+        profiles: vec![(DensityProfile::Halo, 1.0)],
+        neutron_ratio: 0.05,
+        ..SyntheticGalaxy::default()
+    };
+    let mut systems: Vec<StarSystem> = galaxy.generate().into_iter().map(|r| r.into()).collect();
+    systems.sort();
+    let neutrons = neutron_file(&systems, &format!("budget-{}", name));
+    let systems = VecMap::new(systems);
+    // The outermost system and the one closest to its mirror image
+    let start = systems.len() - 1;
+    let (x, y, z) = systems.coords(start);
+    let goal = (0..systems.len()).min_by_key(|&idx| {
+        let (gx, gy, gz) = systems.coords(idx);
+        (gx + x) * (gx + x) + (gy + y) * (gy + y) + (gz + z) * (gz + z)
+    }).unwrap();
+    Case { systems, neutrons, start, goal }
+}
+
+fn search(case: &Case, options: &SearchOptions) -> SearchOutcome {
+    neutron_a_star_with_options(&case.systems, &case.neutrons, case.start, case.goal, Float::from(JUMP_RANGE), options)
+}
+
+#[test]
+fn unlimited_options_match_plain_search() {
+    let case = case("unlimited");
+    for mode in [SearchMode::Fast, SearchMode::Exact] {
+        let route = neutron_a_star_with_mode(&case.systems, &case.neutrons, case.start, case.goal, Float::from(JUMP_RANGE), mode);
+        assert!(route.is_some());
+        assert_eq!(search(&case, &SearchOptions { mode, ..SearchOptions::default() }), SearchOutcome::Found(route.unwrap()));
+    }
+}
+
+#[test]
+fn stops_when_cancelled_or_out_of_time_or_memory() {
+    let case = case("stops");
+    let cancel = CancelToken::new();
+    cancel.clone().cancel();
+    let stopped = [
+        (SearchOptions { cancel: Some(cancel), ..SearchOptions::default() }, StopReason::Cancelled),
+        (SearchOptions { deadline: Some(Instant::now() - Duration::from_secs(1)), ..SearchOptions::default() }, StopReason::Deadline),
+        (SearchOptions { max_expansions: Some(0), ..SearchOptions::default() }, StopReason::ExpansionLimit),
+        (SearchOptions { max_memory: Some(0), ..SearchOptions::default() }, StopReason::MemoryLimit),
+    ];
+    for (options, reason) in stopped {
+        assert_eq!(search(&case, &options), SearchOutcome::Stopped { reason, best: None });
+    }
+}
+
+#[test]
+fn expansion_cap_falls_back_to_best_route_so_far() {
+    let case = case("fallback");
+    let jump_distance = Float::from(JUMP_RANGE);
+    reset_search_counters();
+    a_star(&case.systems, case.start, case.goal, jump_distance, None).unwrap();
+    let direct = search_counters().expansions;
+    reset_search_counters();
+    let full = search(&case, &SearchOptions::default()).route().unwrap();
+    let total = search_counters().expansions + search_counters().neutron_expansions;
+    assert!(total > direct + 1, "{} expansions, {} without neutron stars", total, direct);
+
+    // Past the search without neutron stars, so there's always a route to fall back on
+    let options = SearchOptions { max_expansions: Some(direct + (total - direct) / 2), ..SearchOptions::default() };
+    let SearchOutcome::Stopped { reason: StopReason::ExpansionLimit, best: Some(best) } = search(&case, &options) else {
+        panic!("expected a stopped search with a route");
+    };
+    assert!(is_valid_route(&case.systems, case.start, &best, jump_distance));
+    assert_eq!(best.last(), Some(&case.goal));
+    assert!(best.len() >= full.len());
+}
+
+// Cancels the search once it notes how long the route without neutron stars
+// is, as another thread watching its progress might
+struct CancelAfterDirectRoute(CancelToken);
+
+impl Progress for CancelAfterDirectRoute {
+    fn begin(&self, _task: &str, _total: Option<u64>) {}
+    fn advance(&self, _done: u64) {}
+    fn note(&self, message: &str) {
+        if message.starts_with("No neutron length") {
+            self.0.cancel();
+        }
+    }
+    fn end(&self) {}
+}
+
+#[test]
+fn cancelled_search_falls_back_to_best_route_so_far() {
+    let case = case("cancelled");
+    let jump_distance = Float::from(JUMP_RANGE);
+    let direct = a_star(&case.systems, case.start, case.goal, jump_distance, None).unwrap();
+    reset_search_counters();
+    search(&case, &SearchOptions::default()).route().unwrap();
+    let total = search_counters().expansions + search_counters().neutron_expansions;
+
+    let cancel = CancelToken::new();
+    let options = SearchOptions { cancel: Some(cancel.clone()), progress: Some(Arc::new(CancelAfterDirectRoute(cancel))), ..SearchOptions::default() };
+    reset_search_counters();
+    let outcome = search(&case, &options);
+    // Piecing the route together didn't search on past the cancellation
+    let spent = search_counters().expansions + search_counters().neutron_expansions;
+    assert!(spent < total, "{} expansions, {} for the whole search", spent, total);
+    assert_eq!(outcome, SearchOutcome::Stopped { reason: StopReason::Cancelled, best: Some(direct) });
+}
+
+#[test]
+fn anytime_search_stops_with_its_best_route() {
+    let case = case("anytime");
+    let jump_distance = Float::from(JUMP_RANGE);
+    let mut reported: Vec<AnytimeRoute> = Vec::new();
+    let outcome = neutron_a_star_anytime(&case.systems, &case.neutrons, case.start, case.goal, jump_distance, &ANYTIME_WEIGHTS, &SearchOptions::default(), |r| reported.push(r.clone()));
+    let SearchOutcome::Found(route) = outcome else {
+        panic!("no route");
+    };
+    assert_eq!(reported.last().map(|r| &r.route), Some(&route));
+
+    // Stopped part way through the later passes
+    reset_search_counters();
+    let mut first: Option<AnytimeRoute> = None;
+    neutron_a_star_anytime(&case.systems, &case.neutrons, case.start, case.goal, jump_distance, &ANYTIME_WEIGHTS[..1], &SearchOptions::default(), |r| first = Some(r.clone()));
+    let first_pass = search_counters().expansions + search_counters().neutron_expansions;
+    let first = first.unwrap();
+    let options = SearchOptions { max_expansions: Some(first_pass + 1), ..SearchOptions::default() };
+    let outcome = neutron_a_star_anytime(&case.systems, &case.neutrons, case.start, case.goal, jump_distance, &ANYTIME_WEIGHTS, &options, |_| ());
+    assert_eq!(outcome, SearchOutcome::Stopped { reason: StopReason::ExpansionLimit, best: Some(first.route) });
+}
@@ -0,0 +1,58 @@
+use neutron_route_finder::*;
+
+fn cached_map(name: &str, policy: CachePolicy) -> CachedIndexedFileMap<u32, u32> {
+    let path = std::env::temp_dir().join(format!("neutron_route_finder-cache-{}-{}.idx", name, std::process::id()));
+    let path = path.to_str().unwrap();
+    let data: Vec<u32> = (0..10).map(|i| i * 100).collect();
+    write_indexed_file(&data, path).unwrap();
+    let map = CachedIndexedFileMap::with_policy(path, policy);
+    std::fs::remove_file(path).unwrap();
+    map
+}
+
+// Reads `idxs` in turn, checking each value, and returns which of them were hits
+fn hits(map: &CachedIndexedFileMap<u32, u32>, idxs: &[u32]) -> Vec<bool> {
+    idxs.iter().map(|&idx| {
+        let before = map.stats().hits;
+        assert_eq!(*map.get(idx), idx * 100);
+        map.stats().hits > before
+    }).collect()
+}
+
+#[test]
+fn unbounded_cache_keeps_everything() {
+    let map = cached_map("unbounded", CachePolicy::Unbounded);
+    assert_eq!(hits(&map, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]), [false; 10]);
+    assert_eq!(hits(&map, &[9, 0, 5]), [true; 3]);
+    assert_eq!(map.stats(), CacheStats { hits: 3, misses: 10, evictions: 0 });
+}
+
+#[test]
+fn clock_cache_holds_at_most_its_capacity() {
+    let map = cached_map("capacity", CachePolicy::Clock { capacity: 3 });
+    assert_eq!(hits(&map, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]), [false; 10]);
+    assert_eq!(map.stats(), CacheStats { hits: 0, misses: 10, evictions: 7 });
+    // Only the last three are still there
+    assert_eq!(hits(&map, &[7, 8, 9]), [true; 3]);
+    assert_eq!(hits(&map, &[0]), [false]);
+    assert_eq!(map.stats().evictions, 8);
+}
+
+#[test]
+fn clock_cache_gives_referenced_entries_a_second_chance() {
+    let map = cached_map("order", CachePolicy::Clock { capacity: 2 });
+    assert_eq!(hits(&map, &[0, 1]), [false, false]);
+    // Both are referenced, so the hand clears both and comes back round to 0
+    assert_eq!(hits(&map, &[2]), [false]);
+    // Reading 1 marks it again, but the hand is on it: it clears 1 and 2 and
+    // evicts 1 when it comes round, since 1 wasn't read again after that
+    assert_eq!(hits(&map, &[1, 0]), [true, false]);
+    assert_eq!(hits(&map, &[2, 0, 1]), [true, true, false]);
+    assert_eq!(map.stats(), CacheStats { hits: 3, misses: 5, evictions: 3 });
+}
+
+#[test]
+#[should_panic(expected = "capacity must be at least 1")]
+fn clock_cache_needs_room_for_one_entry() {
+    cached_map("empty", CachePolicy::Clock { capacity: 0 });
+}
@@ -0,0 +1,72 @@
+use neutron_route_finder::*;
+
+fn systems() -> Vec<StarSystem> {
+    let mut systems: Vec<StarSystem> = [("Sol", 0.0, false), ("Ægir", 12.0, true), ("Col 285 Sector ZE-A d1", 80.0, false)].iter().map(|&(name, x, is_neutron)| StarSystem {
+        id64: name_id64(name),
+        name: name.to_string(),
+        main_star_type: if is_neutron { "Neutron Star" } else { "G (White-Yellow) Star" }.to_string(),
+        coords: (x.into(), 0.0.into(), 0.0.into()),
+        distance_from_sol: x.into(),
+        is_neutron,
+    }).collect();
+    systems.sort();
+    systems
+}
+
+// Writes `systems()`, lets `corrupt` change the bytes and opens the result
+fn open_corrupted(name: &str, corrupt: impl FnOnce(&mut Vec<u8>)) -> ColumnarSystems {
+    let path = std::env::temp_dir().join(format!("neutron_route_finder-columnar-{}-{}.cols", name, std::process::id()));
+    let path = path.to_str().unwrap();
+    write_columnar_systems(&systems(), path).unwrap();
+    let mut bytes = std::fs::read(path).unwrap();
+    corrupt(&mut bytes);
+    std::fs::write(path, &bytes).unwrap();
+    let columnar = std::panic::catch_unwind(|| ColumnarSystems::new(path));
+    std::fs::remove_file(path).unwrap();
+    columnar.unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+
+// Where the names start: header, four f32 columns, offsets, id64s, id64 order and star types
+const NAMES: usize = 32 + 3 * 16 + 4 * 8 + 3 * 8 + 3 * 4 + 3;
+
+#[test]
+fn reads_back_what_was_written() {
+    let columnar = open_corrupted("intact", |_| ());
+    for (idx, system) in systems().iter().enumerate() {
+        let read = columnar.get(idx as u32);
+        assert_eq!((read.name, read.main_star_type, read.id64, read.is_neutron), (system.name.clone(), system.main_star_type.clone(), system.id64, system.is_neutron));
+        assert_eq!(columnar.find_by_id64(system.id64), Some(idx as u32));
+    }
+}
+
+#[test]
+#[should_panic(expected = "don't fit in a file")]
+fn rejects_overflowing_counts() {
+    open_corrupted("overflow", |bytes| bytes[8..16].copy_from_slice(&(u64::MAX / 8).to_le_bytes()));
+}
+
+#[test]
+#[should_panic(expected = "names aren't UTF-8")]
+fn rejects_names_that_are_not_utf8() {
+    open_corrupted("utf8", |bytes| bytes[NAMES] = 0xff);
+}
+
+#[test]
+#[should_panic(expected = "splits a character")]
+fn rejects_offsets_inside_a_character() {
+    // "Sol" then "Ægir": move the second offset one byte into the Æ
+    open_corrupted("boundary", |bytes| bytes[32 + 3 * 16 + 8..32 + 3 * 16 + 16].copy_from_slice(&4u64.to_le_bytes()));
+}
+
+#[test]
+#[should_panic(expected = "star type is out of range")]
+fn rejects_star_types_past_the_table() {
+    // Two star types were written, so index 2 points past the table
+    open_corrupted("star_type", |bytes| bytes[NAMES - 1] = 2);
+}
+
+#[test]
+#[should_panic(expected = "id64 order entry is out of range")]
+fn rejects_id64_order_past_the_systems() {
+    open_corrupted("id64_order", |bytes| bytes[NAMES - 3 - 4..NAMES - 3].copy_from_slice(&3u32.to_le_bytes()));
+}
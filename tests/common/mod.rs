@@ -0,0 +1,23 @@
+// Fixtures shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use neutron_route_finder::*;
+
+// Cooked range of the neutron graphs the tests search
+pub const NEUTRON_GRAPH_JUMP_RANGE: f32 = 400.0;
+
+// Writes `neutrons` to a temp file and maps it. `name` keeps apart the files
+// of tests running at the same time.
+pub fn neutron_file_map(neutrons: &[NeutronStarSystem], name: &str) -> NeutronFileMap {
+    let path = std::env::temp_dir().join(format!("neutron_route_finder-{}-{}.nbr", name, std::process::id()));
+    let path = path.to_str().unwrap();
+    write_neutron_file(neutrons, path).unwrap();
+    let map = NeutronFileMap::new(path);
+    std::fs::remove_file(path).unwrap();
+    map
+}
+
+// The neutron graph of `systems`, as `neutron_file_map` maps it
+pub fn neutron_file(systems: &[StarSystem], name: &str) -> NeutronFileMap {
+    neutron_file_map(&make_neutron_star_systems(systems, NEUTRON_GRAPH_JUMP_RANGE), name)
+}
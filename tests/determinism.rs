@@ -0,0 +1,105 @@
+mod common;
+
+use neutron_route_finder::*;
+use common::neutron_file;
+
+const JUMP_RANGE: f32 = 15.0;
+
+// A 12x12x3 grid 10 ly apart, offset so no system sits on an axis, with a few neutron stars. Full of systems at the
+// same distance from Sol and of equally short routes, so any tie left to
+// chance shows up.
+fn lattice_points() -> Vec<(String, (f32, f32, f32), bool)> {
+    let mut points = Vec::new();
+    for x in -6..6 {
+        for y in -6..6 {
+            for z in -1..2 {
+                let is_neutron = (x + 6) % 5 == 2 && (y + 6) % 5 == 2 && z == 0;
+                points.push((format!("Grid {} {} {}", x, y, z), (x as f32 * 10.0 + 5.0, y as f32 * 10.0 + 5.0, z as f32 * 10.0), is_neutron));
+            }
+        }
+    }
+    points
+}
+
+fn galaxy(points: &[(String, (f32, f32, f32), bool)]) -> Vec<StarSystem> {
+    let mut systems: Vec<StarSystem> = points.iter().map(|(name, (x, y, z), is_neutron)| StarSystem {
+        id64: name_id64(name),
+        name: name.clone(),
+        main_star_type: if *is_neutron { "Neutron Star" } else { "M (Red dwarf) Star" }.to_string(),
+        coords: ((*x).into(), (*y).into(), (*z).into()),
+        distance_from_sol: (x * x + y * y + z * z).sqrt().into(),
+        is_neutron: *is_neutron,
+    }).collect();
+    systems.sort();
+    systems
+}
+
+const ROUTES: [(&str, &str); 3] = [
+    ("Grid -6 -6 -1", "Grid 5 5 1"),
+    ("Grid 0 0 0", "Grid -6 5 1"),
+    ("Grid 5 -6 0", "Grid -4 3 -1"),
+];
+
+// Every route in ROUTES, by system name, from each of the searches
+fn all_routes(points: &[(String, (f32, f32, f32), bool)], name: &str) -> Vec<Vec<String>> {
+    let systems = galaxy(points);
+    let neutrons = neutron_file(&systems, &format!("determinism-{}", name));
+    let systems = VecMap::new(systems);
+    let jump_distance = Float::from(JUMP_RANGE);
+    let mut routes = Vec::new();
+    for (start, goal) in ROUTES {
+        let [Some(start), Some(goal)] = find_systems_by_name(&systems, &[start, goal])[..] else {
+            panic!("{} or {} missing", start, goal);
+        };
+        for mode in [SearchMode::Fast, SearchMode::Exact] {
+            let route = a_star_with_mode(&systems, start, goal, jump_distance, None, mode).unwrap();
+            routes.push(route.iter().map(|&idx| systems.name(idx).to_string()).collect());
+            let route = neutron_a_star_with_mode(&systems, &neutrons, start, goal, jump_distance, mode).unwrap();
+            routes.push(route.iter().map(|&idx| systems.name(idx).to_string()).collect());
+        }
+    }
+    routes
+}
+
+#[test]
+fn routes_do_not_depend_on_input_order() {
+    let points = lattice_points();
+    let expected = all_routes(&points, "ordered");
+
+    let mut reversed = points.clone();
+    reversed.reverse();
+    assert_eq!(all_routes(&reversed, "reversed"), expected);
+
+    // A fixed shuffle
+    let mut shuffled = points.clone();
+    let mut state: u64 = 12345;
+    for i in (1..shuffled.len()).rev() {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        shuffled.swap(i, (state >> 33) as usize % (i + 1));
+    }
+    assert_eq!(all_routes(&shuffled, "shuffled"), expected);
+}
+
+// What the searches return for ROUTES. A change here means the same data now
+// gives different routes than earlier builds did.
+const PINNED: [&[&str]; 12] = [
+    &["Grid -5 -5 -1", "Grid -4 -4 -1", "Grid -4 -4 0", "Grid 1 -4 0", "Grid 1 1 0", "Grid 5 5 1"],
+    &["Grid -5 -5 -1", "Grid -4 -4 -1", "Grid -4 -4 0", "Grid 1 -4 0", "Grid 1 1 0", "Grid 5 5 1"],
+    &["Grid -5 -5 -1", "Grid -4 -4 -1", "Grid -4 -4 0", "Grid 0 0 0", "Grid 1 1 0", "Grid 5 5 1"],
+    &["Grid -5 -5 -1", "Grid -4 -4 -1", "Grid -4 -4 0", "Grid 0 0 0", "Grid 1 1 0", "Grid 5 5 1"],
+    &["Grid 1 1 0", "Grid -4 1 0", "Grid -6 5 1"],
+    &["Grid 1 1 0", "Grid -4 1 0", "Grid -6 5 1"],
+    &["Grid 1 1 0", "Grid -4 1 0", "Grid -6 5 1"],
+    &["Grid 1 1 0", "Grid -4 1 0", "Grid -6 5 1"],
+    &["Grid 4 -5 0", "Grid 3 -4 0", "Grid 2 -3 0", "Grid 1 -4 0", "Grid 1 1 0", "Grid -4 3 -1"],
+    &["Grid 4 -5 0", "Grid 3 -4 0", "Grid 2 -3 0", "Grid 1 -4 0", "Grid 1 1 0", "Grid -4 3 -1"],
+    &["Grid 4 -5 0", "Grid 3 -4 0", "Grid 2 -4 0", "Grid 1 -4 0", "Grid 1 1 0", "Grid -4 3 -1"],
+    &["Grid 4 -5 0", "Grid 3 -4 0", "Grid 2 -4 0", "Grid 1 -4 0", "Grid 1 1 0", "Grid -4 3 -1"],
+];
+
+#[test]
+fn pinned_routes() {
+    let routes = all_routes(&lattice_points(), "pinned");
+    let pinned: Vec<Vec<String>> = PINNED.iter().map(|route| route.iter().map(|name| name.to_string()).collect()).collect();
+    assert_eq!(routes, pinned);
+}
@@ -0,0 +1,98 @@
+use neutron_route_finder::*;
+
+fn jump(name: &str, id64: u64, x: f32) -> String {
+    format!(r#"{{"event":"FSDJump","StarSystem":"{}","SystemAddress":{},"StarPos":[{},0.0,0.0]}}"#, name, id64, x)
+}
+
+fn scan(name: &str, id64: u64, star_type: &str) -> String {
+    format!(r#"{{"event":"Scan","StarSystem":"{}","SystemAddress":{},"StarType":"{}","DistanceFromArrivalLS":0.0}}"#, name, id64, star_type)
+}
+
+#[test]
+fn systems_sharing_a_name_are_kept_apart() {
+    let mut ingestor = JournalIngestor::new();
+    for line in [jump("Twin", 1, 10.0), jump("Twin", 2, 20.0), scan("Twin", 2, "N"), scan("Twin", 1, "M")] {
+        ingestor.process_line(&line);
+    }
+    assert_eq!(ingestor.pending_len(), 0);
+    let ready: Vec<(Option<u64>, f32, String)> = ingestor.take_ready().into_iter().map(|r| (r.id64, r.Coord_X, r.mainStarType)).collect();
+    assert_eq!(ready, [(Some(2), 20.0, "Neutron Star".to_string()), (Some(1), 10.0, "M (Red dwarf) Star".to_string())]);
+}
+
+#[test]
+fn events_without_a_system_address_find_their_system_by_name() {
+    let scan_without_id64 = |name: &str| format!(r#"{{"event":"Scan","StarSystem":"{}","StarType":"K","DistanceFromArrivalLS":0.0}}"#, name);
+    let mut ingestor = JournalIngestor::new();
+    // Address first, then a scan without one, and the other way around
+    for line in [jump("Addressed", 1, 10.0), scan_without_id64("Addressed"), scan_without_id64("Scanned first"), jump("Scanned first", 2, 20.0)] {
+        ingestor.process_line(&line);
+    }
+    assert_eq!(ingestor.pending_len(), 0);
+    let ready: Vec<(String, Option<u64>, f32)> = ingestor.take_ready().into_iter().map(|r| (r.name, r.id64, r.Coord_X)).collect();
+    assert_eq!(ready, [("Addressed".to_string(), Some(1), 10.0), ("Scanned first".to_string(), Some(2), 20.0)]);
+
+    // With two systems pending under the name there's no telling which it was
+    for line in [jump("Twin", 3, 30.0), jump("Twin", 4, 40.0), scan_without_id64("Twin")] {
+        ingestor.process_line(&line);
+    }
+    assert_eq!(ingestor.pending_len(), 3);
+    assert!(ingestor.take_ready().is_empty());
+}
+
+#[test]
+fn systems_never_scanned_are_dropped_oldest_first() {
+    let mut ingestor = JournalIngestor::new();
+    ingestor.max_pending = 4;
+    for id64 in 0..10 {
+        ingestor.process_line(&jump(&format!("Unscanned {}", id64), id64, id64 as f32));
+    }
+    assert!(ingestor.pending_len() <= 4, "{} pending", ingestor.pending_len());
+    assert_eq!(ingestor.pending_len() + ingestor.pending_dropped, 10);
+
+    // The oldest is gone, the newest is still waiting for its scan
+    ingestor.process_line(&scan("Unscanned 0", 0, "K"));
+    ingestor.process_line(&scan("Unscanned 9", 9, "K"));
+    let ready: Vec<Option<u64>> = ingestor.take_ready().into_iter().map(|r| r.id64).collect();
+    assert_eq!(ready, [Some(9)]);
+}
+
+#[test]
+fn batches_are_reported_as_they_are_written() {
+    let dir = std::env::temp_dir().join(format!("neutron_route_finder-journal-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let lines = [jump("A", 1, 1.0), scan("A", 1, "N"), jump("B", 2, 2.0), scan("B", 2, "G"), jump("C", 3, 3.0), scan("C", 3, "K")].join("\n");
+    let mut batches = Vec::new();
+    let mut ingestor = JournalIngestor::new();
+    let written = ingest_journal(lines.as_bytes(), &mut ingestor, dir.to_str().unwrap(), 2, |path, count| batches.push((path.to_string(), count))).unwrap();
+    assert_eq!(batches.iter().map(|(_, count)| *count).collect::<Vec<_>>(), [2, 1]);
+    assert_eq!(batches.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>(), written);
+    assert_eq!(read_delta(&written[1]).upserts.iter().map(|r| r.id64).collect::<Vec<_>>(), [Some(3)]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// A connection that drops after whatever came before it
+struct Reset;
+
+impl std::io::Read for Reset {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::ConnectionReset.into())
+    }
+}
+
+#[test]
+fn ready_systems_are_written_when_the_feed_fails() {
+    use std::io::Read;
+    let dir = std::env::temp_dir().join(format!("neutron_route_finder-journal-reset-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let lines = [jump("A", 1, 1.0), scan("A", 1, "N"), jump("B", 2, 2.0), String::new()].join("\n");
+    let mut batches = Vec::new();
+    let mut ingestor = JournalIngestor::new();
+    let reader = std::io::BufReader::new(lines.as_bytes().chain(Reset));
+    let err = ingest_journal(reader, &mut ingestor, dir.to_str().unwrap(), 10, |path, count| batches.push((path.to_string(), count))).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+    assert_eq!(batches.len(), 1);
+    assert_eq!(read_delta(&batches[0].0).upserts.iter().map(|r| r.id64).collect::<Vec<_>>(), [Some(1)]);
+    // B is still waiting for its scan when the feed comes back
+    assert_eq!(ingestor.pending_len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
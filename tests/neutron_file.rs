@@ -0,0 +1,155 @@
+use neutron_route_finder::*;
+
+fn neutrons() -> Vec<NeutronStarSystem> {
+    vec![
+        NeutronStarSystem { idx: 3, id64: 30, neighbors: Box::new([1, 2]) },
+        NeutronStarSystem { idx: 5, id64: 50, neighbors: Box::new([]) },
+        NeutronStarSystem { idx: 8, id64: 80, neighbors: Box::new([0]) },
+    ]
+}
+
+fn path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("neutron_route_finder-neutron-file-{}-{}.nbr", name, std::process::id()));
+    path.to_str().unwrap().to_string()
+}
+
+// Writes `neutrons()`, lets `corrupt` change the bytes and opens the result
+fn open_corrupted(name: &str, corrupt: impl FnOnce(&mut Vec<u8>)) -> NeutronFileMap {
+    let path = path(name);
+    write_neutron_file(&neutrons(), &path).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    corrupt(&mut bytes);
+    std::fs::write(&path, &bytes).unwrap();
+    let map = std::panic::catch_unwind(|| NeutronFileMap::new(&path));
+    std::fs::remove_file(&path).unwrap();
+    map.unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+
+fn set_u64(bytes: &mut [u8], at: usize, value: u64) {
+    bytes[at..at + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+// Header is 24 bytes, then 4 offsets, 3 id64s, 3 idxs and 3 neighbors
+const OFFSETS: usize = 24;
+const NEIGHBORS: usize = OFFSETS + 4 * 8 + 3 * 8 + 3 * 4;
+
+#[test]
+fn reads_back_what_was_written() {
+    let map = open_corrupted("intact", |_| ());
+    assert_eq!(map.len(), 3);
+    for (n_idx, expected) in neutrons().iter().enumerate() {
+        let n = map.get(n_idx as u32);
+        assert_eq!((n.idx, n.id64, n.neighbors.clone()), (expected.idx, expected.id64, expected.neighbors.clone()));
+    }
+}
+
+#[test]
+#[should_panic(expected = "don't fit in a file")]
+fn rejects_overflowing_counts() {
+    open_corrupted("overflow", |bytes| set_u64(bytes, 8, u64::MAX / 4));
+}
+
+#[test]
+#[should_panic(expected = "truncated or corrupt")]
+fn rejects_truncated_files() {
+    open_corrupted("truncated", |bytes| bytes.truncate(bytes.len() - 4));
+}
+
+#[test]
+#[should_panic(expected = "offsets are out of order")]
+fn rejects_offsets_past_the_neighbors() {
+    open_corrupted("offsets-past", |bytes| set_u64(bytes, OFFSETS + 8, 1000));
+}
+
+#[test]
+#[should_panic(expected = "offsets are out of order")]
+fn rejects_decreasing_offsets() {
+    // 0, 2, 1, 3
+    open_corrupted("offsets-decreasing", |bytes| set_u64(bytes, OFFSETS + 16, 1));
+}
+
+#[test]
+#[should_panic(expected = "a neighbor is out of range")]
+fn rejects_neighbors_past_the_neutron_stars() {
+    open_corrupted("neighbor", |bytes| bytes[NEIGHBORS..NEIGHBORS + 4].copy_from_slice(&3u32.to_le_bytes()));
+}
+
+// Cooks a small galaxy, then rewrites its columnar file and manifest with the
+// systems as `change` leaves them, keeping the old neutron file
+fn cook_and_change_systems(name: &str, change: impl FnOnce(&mut Vec<StarSystem>)) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("neutron_route_finder-neutron-file-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("systems.csv");
+    let galaxy = SyntheticGalaxy { seed: 5, system_count: 500, radius: 100.0, neutron_ratio: 0.1, ..SyntheticGalaxy::default() }.generate();
+    write_star_system_records_csv(&galaxy, input.to_str().unwrap()).unwrap();
+    let data_dir = dir.join("data");
+    let data_dir_str = data_dir.to_str().unwrap();
+    let mut manifest = cook(&CookOptions {
+        input: input.to_str().unwrap().to_string(),
+        data_dir: data_dir_str.to_string(),
+        max_jump_distance: 200.0,
+        pruning: PruningPolicy::default(),
+        sqlite: false,
+        force: false,
+        progress: std::sync::Arc::new(SilentProgress),
+    }).unwrap();
+
+    let mut systems = read_star_systems_bincode(data_dir.join(pipeline::SYSTEMS_BINCODE_FILE).to_str().unwrap(), |_| true);
+    change(&mut systems);
+    let columnar_file = data_dir.join(pipeline::SYSTEMS_COLUMNAR_FILE);
+    write_columnar_systems(&systems, columnar_file.to_str().unwrap()).unwrap();
+    manifest.system_count = systems.len() as u64;
+    manifest.dataset_hash = dataset_hash(systems.iter().map(|s| (s.id64, s.coords)));
+    let columnar = manifest.files.iter_mut().find(|f| f.stage == "columnar").unwrap();
+    columnar.size = std::fs::metadata(&columnar_file).unwrap().len();
+    columnar.dataset_hash = manifest.dataset_hash;
+    manifest.write(data_dir_str).unwrap();
+    dir
+}
+
+#[test]
+fn neutron_files_follow_their_stars_when_the_systems_move() {
+    // Every neutron star's index shifts down by one
+    let dir = cook_and_change_systems("moved", |systems| {
+        assert!(!systems.remove(0).is_neutron);
+    });
+    let (_, systems, neutrons) = open_data_dir(dir.join("data").to_str().unwrap()).unwrap();
+    assert!(neutrons.is_remapped());
+    assert!(!neutrons.is_empty());
+    for n_idx in 0..neutrons.len() {
+        assert_eq!(systems.id64(neutrons.idx(n_idx)), neutrons.id64(n_idx));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn neutron_files_missing_a_star_are_refused() {
+    let dir = cook_and_change_systems("missing", |systems| {
+        let neutron = systems.iter().position(|s| s.is_neutron).unwrap();
+        systems.remove(neutron);
+    });
+    let err = open_data_dir(dir.join("data").to_str().unwrap()).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("missing from the systems"), "{}", err);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checkpointed_builds_write_the_same_bytes() {
+    // Enough neutron stars for a few chunks, spread out so the lists stay short
+    let galaxy = SyntheticGalaxy { seed: 3, system_count: 25_000, radius: 5000.0, profiles: vec![(DensityProfile::Halo, 1.0)], neutron_ratio: 0.9, ..SyntheticGalaxy::default() }.generate();
+    let systems: Vec<StarSystem> = galaxy.into_iter().map(|r| r.into()).collect();
+    let grid = NeutronGrid::new(systems.as_slice());
+    assert!(grid.chunk_count() > 2);
+    let policy = PruningPolicy { max_distance: 200.0, ..PruningPolicy::default() };
+    let in_memory: Vec<NeutronStarSystem> = (0..grid.len() as u32).map(|n_idx| grid.neutron_star_system(n_idx, Float::from(60.0), &policy)).collect();
+    assert!(in_memory.iter().any(|n| !n.neighbors.is_empty()));
+    let (whole_path, chunked_path) = (path("whole"), path("chunked"));
+    write_neutron_file(&in_memory, &whole_path).unwrap();
+    let chunk_dir = path("chunks");
+    let count = build_neutron_file_checkpointed(&grid, 60.0, &policy, "key", &chunk_dir, &chunked_path, &SilentProgress).unwrap();
+    assert_eq!(count, in_memory.len());
+    assert!(std::fs::read(&whole_path).unwrap() == std::fs::read(&chunked_path).unwrap());
+    std::fs::remove_file(&whole_path).unwrap();
+    std::fs::remove_file(&chunked_path).unwrap();
+}
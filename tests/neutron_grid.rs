@@ -0,0 +1,50 @@
+use neutron_route_finder::*;
+
+// Grid cells are NEUTRON_MAX_DISTANCE / 8 across
+const CELL: f32 = NEUTRON_MAX_DISTANCE / 8.0;
+
+fn neutron_star(name: String, (x, y, z): (f32, f32, f32)) -> StarSystemRecord {
+    StarSystemRecord {
+        id64: Some(name_id64(&name)),
+        name,
+        Coord_X: x,
+        Coord_Y: y,
+        Coord_Z: z,
+        mainStarType: "Neutron Star".to_string(),
+        d_from_sol: (x * x + y * y + z * z).sqrt(),
+    }
+}
+
+#[test]
+fn grid_neighbors_match_the_brute_force_scan() {
+    // Scattered over several cells, plus a lattice right on the cell borders
+    // and just either side of them, where plenty of distances tie
+    let mut records = SyntheticGalaxy {
+        seed: 9,
+        system_count: 1200,
+        radius: 3.5 * CELL,
+        profiles: vec![(DensityProfile::Halo, 1.0)],
+        neutron_ratio: 0.4,
+        ..SyntheticGalaxy::default()
+    }.generate();
+    for i in -3..=3 {
+        for j in -3..=3 {
+            let (x, y) = (i as f32 * CELL, j as f32 * CELL);
+            records.push(neutron_star(format!("Border {} {}", i, j), (x, y, 0.0)));
+            records.push(neutron_star(format!("Border {} {} below", i, j), (x - 0.03125, y, CELL)));
+            records.push(neutron_star(format!("Border {} {} above", i, j), (x + 0.03125, y, -CELL)));
+        }
+    }
+    let mut systems: Vec<StarSystem> = records.into_iter().map(|r| r.into()).collect();
+    systems.sort();
+    let neutron_stars: Vec<u32> = (0..systems.len() as u32).filter(|&idx| systems[idx as usize].is_neutron).collect();
+
+    for max_jump_distance in [60.0, 400.0] {
+        let graph = make_neutron_star_systems(&systems, max_jump_distance);
+        assert_eq!(graph.len(), neutron_stars.len());
+        for (n, &idx) in graph.iter().zip(&neutron_stars) {
+            assert_eq!(n.idx, idx);
+            assert_eq!(n.neighbors, neutron_neighbors(&systems, &neutron_stars, idx, Float::from(max_jump_distance)), "neutron star {}", systems[idx as usize].name);
+        }
+    }
+}
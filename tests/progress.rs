@@ -0,0 +1,171 @@
+mod common;
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use neutron_route_finder::*;
+use common::neutron_file_map;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Begin(String, Option<u64>),
+    Advance(u64),
+    Note(String),
+    End,
+}
+
+#[derive(Default)]
+struct Recorder(Mutex<Vec<Event>>);
+
+impl Progress for Recorder {
+    fn begin(&self, task: &str, total: Option<u64>) {
+        self.0.lock().unwrap().push(Event::Begin(task.to_string(), total));
+    }
+
+    fn advance(&self, done: u64) {
+        self.0.lock().unwrap().push(Event::Advance(done));
+    }
+
+    fn note(&self, message: &str) {
+        self.0.lock().unwrap().push(Event::Note(message.to_string()));
+    }
+
+    fn end(&self) {
+        self.0.lock().unwrap().push(Event::End);
+    }
+}
+
+impl Recorder {
+    fn events(&self) -> Vec<Event> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+// Every begin is ended before the next, and counts never go backwards
+fn assert_well_formed(events: &[Event]) {
+    let mut open = false;
+    let mut done = 0;
+    for event in events {
+        match event {
+            Event::Begin(..) => {
+                assert!(!open, "{:?}", events);
+                open = true;
+                done = 0;
+            }
+            Event::Advance(d) => {
+                assert!(open && *d >= done, "{:?}", events);
+                done = *d;
+            }
+            Event::Note(_) => (),
+            Event::End => {
+                assert!(open, "{:?}", events);
+                open = false;
+            }
+        }
+    }
+    assert!(!open, "{:?}", events);
+}
+
+fn small_galaxy() -> Vec<StarSystemRecord> {
+    SyntheticGalaxy {
+        seed: 11,
+        system_count: 2000,
+        radius: 200.0,
+        profiles: vec![(DensityProfile::Halo, 1.0)],
+        neutron_ratio: 0.05,
+        ..SyntheticGalaxy::default()
+    }.generate()
+}
+
+#[test]
+fn searches_report_progress() {
+    let mut systems: Vec<StarSystem> = small_galaxy().into_iter().map(|r| r.into()).collect();
+    systems.sort();
+    let recorder = Arc::new(Recorder::default());
+    let neutrons = make_neutron_star_systems_with_policy(&systems, 400.0, &PruningPolicy::default(), recorder.as_ref());
+    let neutron_count = neutrons.len() as u64;
+    let events = recorder.events();
+    assert_well_formed(&events);
+    assert_eq!(events.first(), Some(&Event::Begin("Neutron graph".to_string(), Some(neutron_count))));
+    assert_eq!(events[events.len() - 2], Event::Advance(neutron_count));
+
+    let neutron_map = neutron_file_map(&neutrons, "progress");
+    let systems = VecMap::new(systems);
+    let (start, goal) = (0, systems.len() / 2);
+
+    let recorder = Arc::new(Recorder::default());
+    let options = SearchOptions { progress: Some(recorder.clone()), ..SearchOptions::default() };
+    reset_search_counters();
+    let route = neutron_a_star_with_options(&systems, &neutron_map, start, goal, Float::from(30.0), &options);
+    assert!(matches!(route, SearchOutcome::Found(_)));
+    let counters = search_counters();
+    let events = recorder.events();
+    assert_well_formed(&events);
+    assert!(matches!(&events[0], Event::Begin(task, None) if task.starts_with("Neutron route")));
+    assert!(events.contains(&Event::Note(format!("Neutron star count: {}", neutron_count))));
+    assert_eq!(events[events.len() - 2], Event::Advance(counters.expansions + counters.neutron_expansions));
+}
+
+#[test]
+fn cooking_reports_each_stage() {
+    let dir = std::env::temp_dir().join(format!("neutron_route_finder-progress-cook-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("systems.csv");
+    write_star_system_records_csv(&small_galaxy(), input.to_str().unwrap()).unwrap();
+    let recorder = Arc::new(Recorder::default());
+    let mut options = CookOptions {
+        input: input.to_str().unwrap().to_string(),
+        data_dir: dir.join("data").to_str().unwrap().to_string(),
+        max_jump_distance: 400.0,
+        pruning: PruningPolicy::default(),
+        sqlite: false,
+        force: false,
+        progress: recorder.clone(),
+    };
+    cook(&options).unwrap();
+    let events = recorder.events();
+    assert_well_formed(&events);
+    let notes: Vec<&str> = events.iter().filter_map(|e| match e { Event::Note(note) => Some(note.as_str()), _ => None }).collect();
+    assert_eq!(notes.len(), 3, "{:?}", notes);
+    assert!(notes.iter().zip(["[import]", "[columnar]", "[neutrons]"]).all(|(note, stage)| note.starts_with(stage)));
+    assert!(events.iter().any(|e| matches!(e, Event::Begin(task, Some(_)) if task == "Neutron graph")));
+
+    // Nothing to do the second time round
+    let recorder = Arc::new(Recorder::default());
+    options.progress = recorder.clone();
+    cook(&options).unwrap();
+    assert_eq!(recorder.events(), ["[import]", "[columnar]", "[neutrons]"].map(|stage| Event::Note(format!("{} up to date", stage))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn log_lines_are_key_value() {
+    let buffer = SharedBuffer::default();
+    let log = LogProgress::to_writer(Box::new(buffer.clone()));
+    log.note("starting");
+    log.begin("Neutron graph", Some(10));
+    log.advance(4);
+    log.note("half \"way\"");
+    log.end();
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", output);
+    assert_eq!(lines[0], "progress event=note message=\"starting\"");
+    assert!(lines[1].starts_with("progress event=begin task=\"Neutron graph\" done=0 elapsed_ms="));
+    assert!(lines[1].ends_with(" total=10"));
+    assert!(lines[2].starts_with("progress event=note task=\"Neutron graph\" done=4 "));
+    assert!(lines[2].ends_with(" message=\"half \\\"way\\\"\""));
+    assert!(lines[3].starts_with("progress event=end task=\"Neutron graph\" done=4 "));
+}
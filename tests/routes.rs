@@ -0,0 +1,179 @@
+mod common;
+
+use proptest::prelude::*;
+use neutron_route_finder::*;
+use common::neutron_file;
+
+fn galaxy(points: Vec<((f32, f32, f32), bool)>) -> Vec<StarSystem> {
+    let mut systems: Vec<StarSystem> = points.into_iter().enumerate().map(|(i, ((x, y, z), is_neutron))| {
+        let name = format!("S{}", i);
+        StarSystem {
+            id64: name_id64(&name),
+            name,
+            main_star_type: if is_neutron { "Neutron Star" } else { "K (Yellow-Orange) Star" }.to_string(),
+            coords: (x.into(), y.into(), z.into()),
+            distance_from_sol: (x * x + y * y + z * z).sqrt().into(),
+            is_neutron,
+        }
+    }).collect();
+    systems.sort();
+    systems
+}
+
+// Small, dense enough that most pairs are connected but routes take a few jumps
+fn galaxy_strategy(max_systems: usize, neutron_chance: f64) -> impl Strategy<Value = Vec<StarSystem>> {
+    prop::collection::vec(((-150.0f32..150.0, -150.0f32..150.0, -150.0f32..150.0), prop::bool::weighted(neutron_chance)), 2..max_systems).prop_map(galaxy)
+}
+
+fn route_case(max_systems: usize, neutron_chance: f64) -> impl Strategy<Value = (Vec<StarSystem>, u32, u32, f32)> {
+    galaxy_strategy(max_systems, neutron_chance).prop_flat_map(|systems| {
+        let len = systems.len() as u32;
+        (Just(systems), 0..len, 0..len, 20.0f32..80.0)
+    }).prop_filter("start and goal differ", |(_, start, goal, _)| start != goal)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn exact_a_star_matches_reference((systems, start, goal, jump_distance) in route_case(40, 0.2)) {
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let reference = reference_route(&systems, start, goal, jump_distance);
+        let route = a_star_with_mode(&systems, start, goal, jump_distance, None, SearchMode::Exact);
+        prop_assert_eq!(route.is_some(), reference.is_some());
+        if let (Some(route), Some(reference)) = (route, reference) {
+            prop_assert!(is_valid_route(&systems, start, &route, jump_distance));
+            prop_assert_eq!(route.last(), Some(&goal));
+            prop_assert_eq!(route.len(), reference.len());
+        }
+    }
+
+    #[test]
+    fn fast_a_star_routes_are_valid((systems, start, goal, jump_distance) in route_case(40, 0.2)) {
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let reference = reference_route(&systems, start, goal, jump_distance);
+        let route = a_star(&systems, start, goal, jump_distance, None);
+        prop_assert_eq!(route.is_some(), reference.is_some());
+        if let (Some(route), Some(reference)) = (route, reference) {
+            prop_assert!(is_valid_route(&systems, start, &route, jump_distance));
+            prop_assert_eq!(route.last(), Some(&goal));
+            // Fast mode makes no optimality promise, but can't beat the optimum
+            prop_assert!(route.len() >= reference.len());
+        }
+    }
+
+    #[test]
+    fn neutron_a_star_routes_are_valid((systems, start, goal, jump_distance) in route_case(30, 0.3)) {
+        let neutrons = neutron_file(&systems, "routes-fast");
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let reference = reference_route(&systems, start, goal, jump_distance);
+        if let Some(route) = neutron_a_star(&systems, &neutrons, start, goal, jump_distance) {
+            let reference = reference.expect("route found where the reference has none");
+            prop_assert!(is_valid_route(&systems, start, &route, jump_distance));
+            prop_assert_eq!(route.last(), Some(&goal));
+            prop_assert!(route.len() >= reference.len());
+        }
+    }
+
+    #[test]
+    fn exact_neutron_a_star_matches_reference((systems, start, goal, jump_distance) in route_case(30, 0.3)) {
+        let neutrons = neutron_file(&systems, "routes-exact");
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let reference = reference_route(&systems, start, goal, jump_distance);
+        let route = neutron_a_star_with_mode(&systems, &neutrons, start, goal, jump_distance, SearchMode::Exact);
+        prop_assert_eq!(route.is_some(), reference.is_some());
+        if let (Some(route), Some(reference)) = (route, reference) {
+            prop_assert!(is_valid_route(&systems, start, &route, jump_distance));
+            prop_assert_eq!(route.len(), reference.len());
+        }
+    }
+
+    #[test]
+    fn anytime_routes_improve((systems, start, goal, jump_distance) in route_case(30, 0.3)) {
+        let neutrons = neutron_file(&systems, "routes-anytime");
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let reference = reference_route(&systems, start, goal, jump_distance);
+        let mut reported: Vec<AnytimeRoute> = Vec::new();
+        let outcome = neutron_a_star_anytime(&systems, &neutrons, start, goal, jump_distance, &ANYTIME_WEIGHTS, &SearchOptions::default(), |r| reported.push(r.clone()));
+        let Some(reference) = reference else {
+            prop_assert_eq!(outcome, SearchOutcome::NoRoute);
+            return Ok(());
+        };
+        prop_assert_eq!(outcome, SearchOutcome::Found(reported.last().unwrap().route.clone()));
+        for r in &reported {
+            prop_assert!(is_valid_route(&systems, start, &r.route, jump_distance));
+            prop_assert_eq!(r.route.last(), Some(&goal));
+            prop_assert!(r.route.len() >= reference.len());
+            prop_assert!(r.bound >= 1.0);
+            // The fewest jumps the bound allows for is no more than the optimum
+            prop_assert!((r.route.len() as f32 / r.bound).round() as usize <= reference.len(), "bound {} on a {} jump route, the reference takes {}", r.bound, r.route.len(), reference.len());
+        }
+        for w in reported.windows(2) {
+            prop_assert!(w[1].route.len() < w[0].route.len() || (w[1].route.len() == w[0].route.len() && w[1].bound < w[0].bound));
+        }
+    }
+
+    #[test]
+    fn exact_anytime_search_ends_on_the_optimum((systems, start, goal, jump_distance) in route_case(30, 0.3)) {
+        let neutrons = neutron_file(&systems, "routes-anytime-exact");
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let reference = reference_route(&systems, start, goal, jump_distance);
+        let mut reported: Vec<AnytimeRoute> = Vec::new();
+        let options = SearchOptions { mode: SearchMode::Exact, ..SearchOptions::default() };
+        let outcome = neutron_a_star_anytime(&systems, &neutrons, start, goal, jump_distance, &ANYTIME_WEIGHTS, &options, |r| reported.push(r.clone()));
+        let Some(reference) = reference else {
+            prop_assert_eq!(outcome, SearchOutcome::NoRoute);
+            return Ok(());
+        };
+        let last = reported.last().unwrap();
+        prop_assert_eq!(outcome, SearchOutcome::Found(last.route.clone()));
+        prop_assert_eq!(last.route.len(), reference.len());
+        prop_assert_eq!(last.bound, 1.0);
+        prop_assert!(is_valid_route(&systems, start, &last.route, jump_distance));
+    }
+
+    #[test]
+    fn found_routes_pass_the_validator((systems, start, goal, jump_distance) in route_case(40, 0.2)) {
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        if let Some(path) = a_star(&systems, start, goal, jump_distance, None) {
+            let route: Vec<u32> = std::iter::once(start).chain(path).collect();
+            let check = validate_route(&systems, &route, &ShipParams { jump_distance, fuel: None });
+            prop_assert!(check.is_valid(), "{:?}", check.invalid);
+            prop_assert_eq!(check.hops.len(), route.len() - 1);
+        }
+    }
+
+    // Without neutron stars every jump has the same range both ways, so the
+    // fewest jumps there and back are equal. (Boosts break this: a neutron
+    // star's long jump can't be made in reverse.)
+    #[test]
+    fn exact_route_length_is_symmetric_without_neutrons((systems, start, goal, jump_distance) in route_case(40, 0.0)) {
+        let systems = VecMap::new(systems);
+        let jump_distance = Float::from(jump_distance);
+        let there = a_star_with_mode(&systems, start, goal, jump_distance, None, SearchMode::Exact).map(|r| r.len());
+        let back = a_star_with_mode(&systems, goal, start, jump_distance, None, SearchMode::Exact).map(|r| r.len());
+        prop_assert_eq!(there, back);
+    }
+}
+
+// A corrupt coordinate must not panic the sort or the search, and a system
+// that's nowhere can't be jumped to
+#[test]
+fn nan_coordinates_are_unreachable() {
+    let systems = galaxy(vec![((f32::NAN, 0.0, 0.0), false), ((0.0, 0.0, 0.0), false), ((30.0, 0.0, 0.0), false), ((60.0, 0.0, 0.0), false)]);
+    // Sorted last
+    let nan_idx = systems.iter().position(|s| f32::from(s.coords.0).is_nan()).unwrap() as u32;
+    assert_eq!(nan_idx, 3);
+    let systems = VecMap::new(systems);
+    let jump_distance = Float::from(40.0);
+    assert_eq!(a_star_with_mode(&systems, 0, 2, jump_distance, None, SearchMode::Exact), Some(vec![1, 2]));
+    assert_eq!(a_star(&systems, 0, nan_idx, jump_distance, None), None);
+    assert_eq!(reference_route(&systems, 0, nan_idx, jump_distance), None);
+}
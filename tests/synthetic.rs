@@ -0,0 +1,65 @@
+use neutron_route_finder::*;
+use neutron_route_finder::pipeline::{read_star_system_records, write_star_system_records};
+
+fn small_galaxy(seed: u64) -> SyntheticGalaxy {
+    SyntheticGalaxy {
+        seed,
+        system_count: 2000,
+        neutron_ratio: 0.1,
+        ..SyntheticGalaxy::default()
+    }
+}
+
+fn summary(records: &[StarSystemRecord]) -> Vec<(String, u64, [u32; 3], String)> {
+    let mut summary: Vec<_> = records.iter().map(|r| {
+        (r.name.clone(), r.id64.unwrap_or_else(|| name_id64(&r.name)), [r.Coord_X.to_bits(), r.Coord_Y.to_bits(), r.Coord_Z.to_bits()], r.mainStarType.clone())
+    }).collect();
+    summary.sort();
+    summary
+}
+
+#[test]
+fn same_seed_same_galaxy() {
+    let a = small_galaxy(1).generate();
+    let b = small_galaxy(1).generate();
+    let c = small_galaxy(2).generate();
+    assert_eq!(summary(&a), summary(&b));
+    assert_ne!(summary(&a), summary(&c));
+}
+
+#[test]
+fn respects_settings() {
+    let galaxy = small_galaxy(3);
+    let records = galaxy.generate();
+    assert_eq!(records.len(), galaxy.system_count);
+    assert!(records.windows(2).all(|w| w[0].d_from_sol <= w[1].d_from_sol));
+    assert!(records.iter().all(|r| r.d_from_sol <= galaxy.radius));
+    let neutrons = records.iter().filter(|r| r.mainStarType == "Neutron Star").count();
+    assert!((150..250).contains(&neutrons), "{} neutron stars", neutrons);
+    let mut id64s: Vec<u64> = records.iter().map(|r| r.id64.unwrap()).collect();
+    id64s.sort();
+    id64s.dedup();
+    assert_eq!(id64s.len(), records.len());
+
+    let halo_only = SyntheticGalaxy {
+        profiles: vec![(DensityProfile::Halo, 1.0)],
+        star_classes: vec![("Black Hole".to_string(), 1.0)],
+        neutron_ratio: 0.0,
+        ..galaxy
+    }.generate();
+    assert!(halo_only.iter().all(|r| r.mainStarType == "Black Hole" && r.name.starts_with("Synth halo")));
+}
+
+#[test]
+fn round_trips_through_every_format() {
+    let records = small_galaxy(4).generate();
+    let dir = std::env::temp_dir().join(format!("neutron_route_finder-synthetic-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["systems.csv", "systems.json", "systems.jsonl.gz", "systems.sqlite", "systems.bin.gz"] {
+        let path = dir.join(name);
+        let path = path.to_str().unwrap();
+        write_star_system_records(&records, path).unwrap();
+        assert_eq!(summary(&read_star_system_records(path)), summary(&records), "{}", name);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,78 @@
+use neutron_route_finder::*;
+
+fn record(name: &str, id64: u64, x: f32) -> StarSystemRecord {
+    StarSystemRecord {
+        name: name.to_string(),
+        Coord_X: x,
+        Coord_Y: 0.0,
+        Coord_Z: 0.0,
+        mainStarType: "M (Red dwarf) Star".to_string(),
+        d_from_sol: x.abs(),
+        id64: Some(id64),
+    }
+}
+
+fn systems(records: &[StarSystemRecord]) -> Vec<StarSystem> {
+    let mut systems: Vec<StarSystem> = records.iter().cloned().map(|r| r.into()).collect();
+    systems.sort();
+    systems
+}
+
+#[test]
+fn removals_only_drop_the_system_with_that_id64() {
+    let old = systems(&[record("Twin", 1, 10.0), record("Twin", 2, 20.0), record("Other", 3, 30.0)]);
+    let delta = DatasetDelta { upserts: Vec::new(), removals: vec![2] };
+    let (new, summary) = apply_delta(old, delta);
+    assert_eq!(summary.removed, 1);
+    assert_eq!(new.iter().map(|s| (s.name.as_str(), s.id64)).collect::<Vec<_>>(), [("Twin", 1), ("Other", 3)]);
+}
+
+#[test]
+fn diff_deltas_turn_the_old_dataset_into_the_new_one() {
+    let old = vec![record("Twin", 1, 10.0), record("Twin", 2, 20.0), record("Moved", 3, 30.0), record("Renamed", 4, 40.0)];
+    let new = vec![record("Twin", 1, 10.0), record("Moved", 3, 35.0), record("Now Named", 4, 40.0), record("Added", 5, 50.0)];
+    let delta = diff_datasets(old.clone(), new.clone()).to_delta();
+    assert_eq!(delta.removals, [2]);
+    let (patched, summary) = apply_delta(systems(&old), delta);
+    assert_eq!((summary.added, summary.updated, summary.removed), (1, 2, 1));
+    let patched: Vec<(String, u64, V3)> = patched.into_iter().map(|s| (s.name, s.id64, s.coords)).collect();
+    let expected: Vec<(String, u64, V3)> = systems(&new).into_iter().map(|s| (s.name, s.id64, s.coords)).collect();
+    assert_eq!(patched, expected);
+}
+
+#[test]
+fn systems_without_an_id64_are_told_apart_by_position() {
+    let unnamed = |x| StarSystemRecord { id64: None, ..record("Twin", 0, x) };
+    let old = vec![unnamed(10.0), unnamed(20.0)];
+    let new = vec![unnamed(10.0)];
+    assert_ne!(old[0].system_id64(), old[1].system_id64());
+    check_unique_id64s(&old).unwrap();
+    let delta = diff_datasets(old.clone(), new).to_delta();
+    assert_eq!(delta.removals, [old[1].system_id64()]);
+}
+
+#[test]
+fn systems_sharing_an_id64_are_refused() {
+    let records = [record("Twin", 1, 10.0), record("Other", 2, 20.0), record("Twin Again", 1, 30.0)];
+    let err = check_unique_id64s(&records).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("Twin, Twin Again"), "{}", err);
+}
+
+#[test]
+fn systems_without_an_id64_are_followed_when_they_move() {
+    let unnamed = |name, x| StarSystemRecord { id64: None, ..record(name, 0, x) };
+    let old = vec![unnamed("Twin", 10.0), unnamed("Twin", 20.0), unnamed("Corrected", 30.0)];
+    let new = vec![unnamed("Twin", 10.0), unnamed("Twin", 22.0), unnamed("Corrected", 31.0)];
+    let diff = diff_datasets(old.clone(), new.clone());
+    assert!(diff.added.is_empty() && diff.removed.is_empty(), "{:?}", diff);
+    let moved: Vec<(f32, f32)> = diff.moved.iter().map(|(old, new)| (old.Coord_X, new.Coord_X)).collect();
+    assert_eq!(moved, [(20.0, 22.0), (30.0, 31.0)]);
+
+    // The delta moves the existing systems rather than adding new ones
+    let (patched, summary) = apply_delta(systems(&old), diff.to_delta());
+    assert_eq!((summary.added, summary.updated, summary.removed), (0, 2, 0));
+    let patched: Vec<(String, V3)> = patched.into_iter().map(|s| (s.name, s.coords)).collect();
+    let expected: Vec<(String, V3)> = systems(&new).into_iter().map(|s| (s.name, s.coords)).collect();
+    assert_eq!(patched, expected);
+}
@@ -0,0 +1,82 @@
+use neutron_route_finder::*;
+
+// Systems along the x axis
+fn line(stars: &[(f32, &str)]) -> VecMap<StarSystem> {
+    let mut systems: Vec<StarSystem> = stars.iter().enumerate().map(|(i, &(x, main_star_type))| {
+        let name = format!("L{}", i);
+        StarSystem {
+            id64: name_id64(&name),
+            name,
+            main_star_type: main_star_type.to_string(),
+            coords: (x.into(), 0.0.into(), 0.0.into()),
+            distance_from_sol: x.abs().into(),
+            is_neutron: main_star_type == "Neutron Star",
+        }
+    }).collect();
+    systems.sort();
+    VecMap::new(systems)
+}
+
+const K: &str = "K (Yellow-Orange) Star";
+const L: &str = "L (Brown dwarf) Star";
+
+fn ship(jump_distance: f32) -> ShipParams {
+    ShipParams { jump_distance: jump_distance.into(), fuel: None }
+}
+
+#[test]
+fn boosts_only_from_neutron_stars_and_white_dwarfs() {
+    let systems = line(&[(0.0, "Neutron Star"), (150.0, "White Dwarf (DA) Star"), (180.0, K), (220.0, K), (300.0, K)]);
+    let check = validate_route(&systems, &[0, 1, 3], &ship(50.0));
+    assert!(check.is_valid(), "{:?}", check.invalid);
+    assert_eq!(check.hops.iter().map(|h| h.boost).collect::<Vec<_>>(), vec![4.0, 1.5]);
+
+    let invalid = validate_route(&systems, &[0, 1, 3, 4], &ship(50.0)).invalid.unwrap();
+    assert_eq!(invalid.hop, 2);
+    assert_eq!(invalid.problem, HopProblem::NoBoost { range: 50.0 });
+    // Nothing in between to fill the gap with
+    assert_eq!(invalid.repair, None);
+
+    let invalid = validate_route(&systems, &[0, 3], &ship(50.0)).invalid.unwrap();
+    assert_eq!(invalid.problem, HopProblem::OutOfRange { range: 50.0, boost: 4.0 });
+    assert_eq!(invalid.repair, Some(Repair::Insert { position: 1, systems: vec![2] }));
+}
+
+#[test]
+fn suggests_repairs() {
+    let systems = line(&[(0.0, K), (40.0, K), (80.0, K)]);
+    let invalid = validate_route(&systems, &[0, 0, 2], &ship(50.0)).invalid.unwrap();
+    assert_eq!((invalid.hop, invalid.problem, invalid.repair), (0, HopProblem::SameSystem, Some(Repair::Remove { position: 1 })));
+    let invalid = validate_route(&systems, &[0, 2], &ship(50.0)).invalid.unwrap();
+    assert_eq!(invalid.repair, Some(Repair::Insert { position: 1, systems: vec![1] }));
+}
+
+#[test]
+fn tracks_fuel() {
+    let fuel = FuelModel {
+        tank: 4.0,
+        max_fuel_per_jump: 2.0,
+        mass: 175.0,
+        optimal_mass: 1000.0,
+        linear_constant: 12.0,
+        power_constant: 2.45,
+        scoop: false,
+    };
+    let cost = fuel.fuel_cost(40.0, 1.0, fuel.tank);
+    assert!(fuel.max_range(fuel.tank) > 40.0 && cost > 1.4 && cost < 1.6, "{} ly, {} t", fuel.max_range(fuel.tank), cost);
+    // Three 40 ly jumps take more than a tank, unless there's a scoopable star to stop at
+    let ship = ShipParams { jump_distance: 50.0.into(), fuel: Some(fuel) };
+    let systems = line(&[(0.0, L), (40.0, L), (80.0, L), (85.0, K), (120.0, L)]);
+    let invalid = validate_route(&systems, &[0, 1, 2, 4], &ship).invalid.unwrap();
+    assert_eq!(invalid.hop, 2);
+    assert!(matches!(invalid.problem, HopProblem::OutOfFuel { .. }), "{:?}", invalid.problem);
+    assert_eq!(invalid.repair, None);
+
+    let ship = ShipParams { fuel: Some(FuelModel { scoop: true, ..fuel }), ..ship };
+    let invalid = validate_route(&systems, &[0, 1, 2, 4], &ship).invalid.unwrap();
+    assert_eq!(invalid.repair, Some(Repair::Insert { position: 3, systems: vec![3] }));
+    let check = validate_route(&systems, &[0, 1, 2, 3, 4], &ship);
+    assert!(check.is_valid(), "{:?}", check.invalid);
+    assert!(check.hops[2].refuelled);
+    assert_eq!(check.hops[2].fuel_left, Some(fuel.tank));
+}